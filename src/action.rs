@@ -0,0 +1,214 @@
+use std::{fs, io, path::Path, process::Command};
+
+use url::Url;
+
+use crate::{
+    clipboard::{Contents, Pasteboard},
+    pasteboard::MacPasteboard,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Open,
+    Reveal,
+    CopyPath,
+}
+
+impl Action {
+    pub fn run(self, path: &Path, launcher: &dyn Launcher) -> io::Result<()> {
+        match self {
+            Action::Open => launcher.open(path),
+            Action::Reveal => launcher.reveal(path),
+            Action::CopyPath => launcher.copy(&path.to_string_lossy()),
+        }
+    }
+
+    // there's nothing to reveal for a url, so it's opened
+    pub fn run_url(self, url: &Url, launcher: &dyn Launcher) -> io::Result<()> {
+        match self {
+            Action::Open | Action::Reveal => launcher.open_url(url),
+            Action::CopyPath => launcher.copy(url.as_str()),
+        }
+    }
+}
+
+// everything that touches the outside world goes through here so it can be swapped out
+pub trait Launcher {
+    fn open(&self, path: &Path) -> io::Result<()>;
+    fn reveal(&self, path: &Path) -> io::Result<()>;
+    // in the default browser, or whatever handles the scheme
+    fn open_url(&self, url: &Url) -> io::Result<()>;
+    // opens a text file in the default editor, creating it if needed
+    fn edit(&self, path: &Path) -> io::Result<()>;
+    fn copy(&self, text: &str) -> io::Result<()> {
+        self.copy_contents(&Contents::Text(text.to_string()))
+    }
+    fn copy_contents(&self, contents: &Contents) -> io::Result<()>;
+    // pastes the clipboard into the frontmost application, then moves the cursor `left`
+    // characters back
+    fn paste(&self, left: usize) -> io::Result<()>;
+    fn notify(&self, title: &str, message: &str) -> io::Result<()>;
+}
+
+pub struct SystemLauncher;
+
+impl Launcher for SystemLauncher {
+    fn open(&self, path: &Path) -> io::Result<()> {
+        Command::new("open").arg(path).spawn().map(|_| ())
+    }
+
+    fn reveal(&self, path: &Path) -> io::Result<()> {
+        Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+    }
+
+    fn open_url(&self, url: &Url) -> io::Result<()> {
+        Command::new("open").arg(url.as_str()).spawn().map(|_| ())
+    }
+
+    fn edit(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Command::new("open").arg("-t").arg(path).spawn().map(|_| ())
+    }
+
+    fn copy_contents(&self, contents: &Contents) -> io::Result<()> {
+        MacPasteboard.write(contents)
+    }
+
+    // needs accessibility access for System Events, the short delay lets the application
+    // behind the panel take focus back first
+    fn paste(&self, left: usize) -> io::Result<()> {
+        let script = format!(
+            "delay 0.1\n\
+             tell application \"System Events\"\n\
+             keystroke \"v\" using command down\n\
+             repeat {left} times\n\
+             key code 123\n\
+             end repeat\n\
+             end tell"
+        );
+        Command::new("osascript")
+            .arg("-e")
+            .arg(script)
+            .spawn()
+            .map(|_| ())
+    }
+
+    fn notify(&self, title: &str, message: &str) -> io::Result<()> {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(message),
+            applescript_string(title)
+        );
+        Command::new("osascript")
+            .arg("-e")
+            .arg(script)
+            .spawn()
+            .map(|_| ())
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// records what it was asked to do instead of doing it
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingLauncher {
+    pub calls: std::cell::RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl RecordingLauncher {
+    fn record(&self, call: String) -> io::Result<()> {
+        self.calls.borrow_mut().push(call);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl Launcher for RecordingLauncher {
+    fn open(&self, path: &Path) -> io::Result<()> {
+        self.record(format!("open {}", path.display()))
+    }
+
+    fn reveal(&self, path: &Path) -> io::Result<()> {
+        self.record(format!("reveal {}", path.display()))
+    }
+
+    fn open_url(&self, url: &Url) -> io::Result<()> {
+        self.record(format!("open_url {url}"))
+    }
+
+    fn edit(&self, path: &Path) -> io::Result<()> {
+        self.record(format!("edit {}", path.display()))
+    }
+
+    fn copy_contents(&self, contents: &Contents) -> io::Result<()> {
+        match contents {
+            Contents::Text(text) => self.record(format!("copy {text}")),
+            other => self.record(format!("copy {other:?}")),
+        }
+    }
+
+    fn paste(&self, left: usize) -> io::Result<()> {
+        self.record(format!("paste {left}"))
+    }
+
+    fn notify(&self, title: &str, message: &str) -> io::Result<()> {
+        self.record(format!("notify {title}: {message}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_on_paths() {
+        let launcher = RecordingLauncher::default();
+        let path = Path::new("/Applications/Safari.app");
+        Action::Open.run(path, &launcher).unwrap();
+        Action::Reveal.run(path, &launcher).unwrap();
+        Action::CopyPath.run(path, &launcher).unwrap();
+        assert_eq!(
+            *launcher.calls.borrow(),
+            [
+                "open /Applications/Safari.app",
+                "reveal /Applications/Safari.app",
+                "copy /Applications/Safari.app",
+            ]
+        );
+    }
+
+    #[test]
+    fn actions_on_urls() {
+        let launcher = RecordingLauncher::default();
+        let url = Url::parse("https://example.com/a b").unwrap();
+        Action::Open.run_url(&url, &launcher).unwrap();
+        Action::Reveal.run_url(&url, &launcher).unwrap();
+        Action::CopyPath.run_url(&url, &launcher).unwrap();
+        assert_eq!(
+            *launcher.calls.borrow(),
+            [
+                "open_url https://example.com/a%20b",
+                "open_url https://example.com/a%20b",
+                "copy https://example.com/a%20b",
+            ]
+        );
+    }
+
+    #[test]
+    fn applescript_strings_are_escaped() {
+        assert_eq!(
+            applescript_string(r#"say "hi" \ bye"#),
+            r#""say \"hi\" \\ bye""#
+        );
+    }
+}
@@ -0,0 +1,1061 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::search::{Answer, Answerer};
+
+// starting a query with it hands the rest to the calculator, so even `= 2` is answered
+pub const TRIGGER: &str = "=";
+
+// significant digits shown for results that can't be written out exactly
+const SIGNIFICANT_DIGITS: usize = 12;
+// terminating decimals longer than this are rounded like any other fraction
+const MAX_EXACT_DECIMALS: usize = 20;
+// longer integers are shown in scientific notation
+const MAX_INTEGER_DIGITS: usize = 24;
+// past these, exact powers and factorials get too big to be worth computing
+const MAX_EXACT_POW_BITS: u64 = 1 << 20;
+const MAX_FACTORIAL: u64 = 5000;
+const MAX_EXACT_EXPONENT: i64 = 1000;
+// trig results this close to zero are float noise, e.g. sin(pi)
+const TRIG_EPSILON: f64 = 1e-14;
+
+// rationals stay exact through + - * / and integer powers, anything irrational falls back to f64
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    Exact(BigRational),
+    Approx(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CalcError {
+    Syntax {
+        at: usize,
+        message: String,
+    },
+    UnknownName(String),
+    Arity {
+        name: String,
+        expected: &'static str,
+    },
+    DivisionByZero,
+    Domain(&'static str),
+    Overflow,
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::Syntax { at, message } => write!(f, "{message} at {at}"),
+            CalcError::UnknownName(name) => write!(f, "unknown name `{name}`"),
+            CalcError::Arity { name, expected } => {
+                write!(f, "`{name}` takes {expected}")
+            }
+            CalcError::DivisionByZero => write!(f, "division by zero"),
+            CalcError::Domain(name) => write!(f, "`{name}` is undefined for this input"),
+            CalcError::Overflow => write!(f, "result is too large"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    // `radix` is kept so a bare hex literal still counts as a conversion worth answering
+    Num { value: Number, radix: u32 },
+    Const(&'static str),
+    Neg(Box<Expr>),
+    Percent(Box<Expr>),
+    Factorial(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(&'static str, Vec<Expr>),
+}
+
+impl Expr {
+    // a lone decimal number or constant, evaluating it would just echo the query back
+    pub fn is_trivial(&self) -> bool {
+        match self {
+            Expr::Num { radix, .. } => *radix == 10,
+            Expr::Const(_) => true,
+            Expr::Neg(expr) => expr.is_trivial(),
+            _ => false,
+        }
+    }
+}
+
+pub fn evaluate(input: &str) -> Result<Number, CalcError> {
+    parse(input)?.eval()
+}
+
+pub fn parse(input: &str) -> Result<Expr, CalcError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.len(),
+    };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(syntax(token.at, "unexpected input")),
+    }
+}
+
+fn syntax(at: usize, message: &str) -> CalcError {
+    CalcError::Syntax {
+        at,
+        message: message.to_string(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Num(Number, u32),
+    Ident(String),
+    Op(char),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    // byte offset in the input, for error messages
+    at: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(at, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = number_end(input, at);
+            while chars.peek().is_some_and(|&(i, _)| i < end) {
+                chars.next();
+            }
+            let (value, radix) = number(&input[at..end], at)?;
+            tokens.push(Token {
+                kind: TokenKind::Num(value, radix),
+                at,
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = at;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(input[at..end].to_lowercase()),
+                at,
+            });
+        } else {
+            chars.next();
+            let op = match c {
+                '*' if chars.peek().is_some_and(|&(_, c)| c == '*') => {
+                    chars.next();
+                    '^'
+                }
+                '+' | '-' | '*' | '/' | '^' | '%' | '!' | '(' | ')' | ',' => c,
+                '×' | '·' => '*',
+                '÷' => '/',
+                '−' => '-',
+                _ => return Err(syntax(at, &format!("unexpected `{c}`"))),
+            };
+            tokens.push(Token {
+                kind: TokenKind::Op(op),
+                at,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+// where the number starting at `start` ends, letters only belong to it as a radix prefix, hex
+// digits or an exponent, so `2pi` is two tokens
+fn number_end(input: &str, start: usize) -> usize {
+    let bytes = input.as_bytes();
+    let digits_from = |mut i: usize, hex: bool| {
+        while i < bytes.len()
+            && (bytes[i].is_ascii_digit()
+                || bytes[i] == b'_'
+                || (hex && bytes[i].is_ascii_hexdigit()))
+        {
+            i += 1;
+        }
+        i
+    };
+
+    if bytes[start] == b'0'
+        && matches!(
+            bytes.get(start + 1),
+            Some(b'x' | b'X' | b'o' | b'O' | b'b' | b'B')
+        )
+        && bytes.get(start + 2).is_some_and(|b| b.is_ascii_hexdigit())
+    {
+        return digits_from(start + 2, matches!(bytes[start + 1], b'x' | b'X'));
+    }
+
+    let mut end = digits_from(start, false);
+    if bytes.get(end) == Some(&b'.') {
+        end = digits_from(end + 1, false);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let digits = match bytes.get(end + 1) {
+            Some(b'+' | b'-') => end + 2,
+            _ => end + 1,
+        };
+        if bytes.get(digits).is_some_and(|b| b.is_ascii_digit()) {
+            end = digits_from(digits, false);
+        }
+    }
+    end
+}
+
+// decimal (with optional fraction and exponent), or 0x / 0o / 0b integers, `_` separates digits
+fn number(text: &str, at: usize) -> Result<(Number, u32), CalcError> {
+    let digits = text.replace('_', "");
+    let invalid = || syntax(at, &format!("invalid number `{text}`"));
+
+    let prefixed = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .into_iter()
+        .find_map(|(prefix, radix)| {
+            let rest = digits
+                .strip_prefix(prefix)
+                .or_else(|| digits.strip_prefix(&prefix.to_uppercase()))?;
+            Some((rest, radix))
+        });
+    if let Some((rest, radix)) = prefixed {
+        let value = BigInt::parse_bytes(rest.as_bytes(), radix).ok_or_else(invalid)?;
+        return Ok((Number::Exact(BigRational::from_integer(value)), radix));
+    }
+
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(idx) => {
+            let exponent: i64 = digits[idx + 1..].parse().map_err(|_| invalid())?;
+            (&digits[..idx], exponent)
+        }
+        None => (digits.as_str(), 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (int.is_empty() && frac.is_empty())
+        || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    if exponent.abs() > MAX_EXACT_EXPONENT {
+        let value: f64 = digits.parse().map_err(|_| invalid())?;
+        return Ok((Number::from_f64(value)?, 10));
+    }
+    let numer: BigInt = format!("{int}{frac}").parse().map_err(|_| invalid())?;
+    let scale = exponent - frac.len() as i64;
+    let ten = BigInt::from(10);
+    let value = if scale >= 0 {
+        BigRational::from_integer(numer * num_traits::pow(ten, scale as usize))
+    } else {
+        BigRational::new(numer, num_traits::pow(ten, (-scale) as usize))
+    };
+    Ok((Number::Exact(value), 10))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // reported as the position of errors at the end of the input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_op(&self) -> Option<char> {
+        match self.peek()?.kind {
+            TokenKind::Op(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn peek_ident(&self, name: &str) -> bool {
+        matches!(&self.peek(), Some(Token { kind: TokenKind::Ident(ident), .. }) if ident == name)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect_op(&mut self, op: char) -> Result<(), CalcError> {
+        match self.next() {
+            Some(Token {
+                kind: TokenKind::Op(found),
+                ..
+            }) if found == op => Ok(()),
+            Some(token) => Err(syntax(token.at, &format!("expected `{op}`"))),
+            None => Err(syntax(self.end, &format!("expected `{op}`"))),
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.next();
+            let rhs = self.term()?;
+            let op = if op == '+' {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/' | 'mod') unary | implicit multiplication)*
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token {
+                    kind: TokenKind::Op('*'),
+                    ..
+                }) => BinaryOp::Mul,
+                Some(Token {
+                    kind: TokenKind::Op('/'),
+                    ..
+                }) => BinaryOp::Div,
+                _ if self.peek_ident("mod") => BinaryOp::Mod,
+                // `2pi`, `3(1 + 2)` and `(1 + 2)(3 + 4)`
+                Some(Token {
+                    kind: TokenKind::Op('(') | TokenKind::Ident(_),
+                    ..
+                }) => {
+                    let rhs = self.unary()?;
+                    lhs = Expr::Binary(BinaryOp::Mul, Box::new(lhs), Box::new(rhs));
+                    continue;
+                }
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        match self.peek_op() {
+            Some('-') => {
+                self.next();
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some('+') => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := postfix ('^' unary)?, right associative and binding tighter than a leading minus
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.postfix()?;
+        if self.peek_op() == Some('^') {
+            self.next();
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    // postfix := primary ('%' | '!')*
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let mut expr = self.primary()?;
+        loop {
+            expr = match self.peek_op() {
+                Some('%') => Expr::Percent(Box::new(expr)),
+                Some('!') => Expr::Factorial(Box::new(expr)),
+                _ => return Ok(expr),
+            };
+            self.next();
+        }
+    }
+
+    // primary := number | constant | function '(' args ')' | '(' expr ')'
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        let Some(token) = self.next() else {
+            return Err(syntax(self.end, "unexpected end of input"));
+        };
+        match token.kind {
+            TokenKind::Num(value, radix) => Ok(Expr::Num { value, radix }),
+            TokenKind::Op('(') => {
+                let expr = self.expr()?;
+                self.expect_op(')')?;
+                Ok(expr)
+            }
+            TokenKind::Ident(name) => {
+                if let Some(constant) = CONSTANTS.iter().find(|(n, _)| *n == name) {
+                    return Ok(Expr::Const(constant.0));
+                }
+                let Some(function) = FUNCTIONS.iter().find(|f| f.name == name) else {
+                    return Err(CalcError::UnknownName(name));
+                };
+                self.expect_op('(')?;
+                let mut args = vec![self.expr()?];
+                while self.peek_op() == Some(',') {
+                    self.next();
+                    args.push(self.expr()?);
+                }
+                self.expect_op(')')?;
+                if !function.arity.contains(&args.len()) {
+                    return Err(CalcError::Arity {
+                        name,
+                        expected: function.expected,
+                    });
+                }
+                Ok(Expr::Call(function.name, args))
+            }
+            TokenKind::Op(op) => Err(syntax(token.at, &format!("unexpected `{op}`"))),
+        }
+    }
+}
+
+const CONSTANTS: [(&str, f64); 7] = [
+    ("pi", std::f64::consts::PI),
+    ("π", std::f64::consts::PI),
+    ("tau", std::f64::consts::TAU),
+    ("τ", std::f64::consts::TAU),
+    ("e", std::f64::consts::E),
+    // radians per degree, so `sin(30deg)` reads naturally
+    ("deg", std::f64::consts::PI / 180.0),
+    ("°", std::f64::consts::PI / 180.0),
+];
+
+struct Function {
+    name: &'static str,
+    arity: std::ops::RangeInclusive<usize>,
+    expected: &'static str,
+}
+
+const fn function(
+    name: &'static str,
+    arity: std::ops::RangeInclusive<usize>,
+    expected: &'static str,
+) -> Function {
+    Function {
+        name,
+        arity,
+        expected,
+    }
+}
+
+const FUNCTIONS: [Function; 21] = [
+    function("sqrt", 1..=1, "one argument"),
+    function("cbrt", 1..=1, "one argument"),
+    function("abs", 1..=1, "one argument"),
+    function("floor", 1..=1, "one argument"),
+    function("ceil", 1..=1, "one argument"),
+    function("round", 1..=1, "one argument"),
+    function("sin", 1..=1, "one argument"),
+    function("cos", 1..=1, "one argument"),
+    function("tan", 1..=1, "one argument"),
+    function("asin", 1..=1, "one argument"),
+    function("acos", 1..=1, "one argument"),
+    function("atan", 1..=1, "one argument"),
+    function("sinh", 1..=1, "one argument"),
+    function("cosh", 1..=1, "one argument"),
+    function("tanh", 1..=1, "one argument"),
+    function("ln", 1..=1, "one argument"),
+    function("log", 1..=2, "a value and an optional base"),
+    function("log2", 1..=1, "one argument"),
+    function("exp", 1..=1, "one argument"),
+    function("min", 1..=usize::MAX, "at least one argument"),
+    function("max", 1..=usize::MAX, "at least one argument"),
+];
+
+impl Expr {
+    pub fn eval(&self) -> Result<Number, CalcError> {
+        match self {
+            Expr::Num { value, .. } => Ok(value.clone()),
+            Expr::Const(name) => {
+                let (_, value) = CONSTANTS.iter().find(|(n, _)| n == name).unwrap();
+                Ok(Number::Approx(*value))
+            }
+            Expr::Neg(expr) => expr.eval()?.mul(&Number::from(-1)),
+            Expr::Percent(expr) => expr.eval()?.div(&Number::from(100)),
+            Expr::Factorial(expr) => expr.eval()?.factorial(),
+            // `200 + 10%` is 220, the way every pocket calculator reads it
+            Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), lhs, rhs)
+                if matches!(**rhs, Expr::Percent(_)) =>
+            {
+                let lhs = lhs.eval()?;
+                let fraction = rhs.eval()?;
+                let factor = match op {
+                    BinaryOp::Add => Number::from(1).add(&fraction)?,
+                    _ => Number::from(1).sub(&fraction)?,
+                };
+                lhs.mul(&factor)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval()?, rhs.eval()?);
+                match op {
+                    BinaryOp::Add => lhs.add(&rhs),
+                    BinaryOp::Sub => lhs.sub(&rhs),
+                    BinaryOp::Mul => lhs.mul(&rhs),
+                    BinaryOp::Div => lhs.div(&rhs),
+                    BinaryOp::Mod => lhs.rem(&rhs),
+                    BinaryOp::Pow => lhs.pow(&rhs),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval())
+                    .collect::<Result<Vec<_>, _>>()?;
+                call(name, &args)
+            }
+        }
+    }
+}
+
+fn call(name: &'static str, args: &[Number]) -> Result<Number, CalcError> {
+    let x = &args[0];
+    let approx =
+        |f: fn(f64) -> f64| Number::from_f64(f(x.to_f64())).map_err(|_| CalcError::Domain(name));
+    let trig = |f: fn(f64) -> f64| {
+        let value = f(x.to_f64());
+        let value = if value.abs() < TRIG_EPSILON {
+            0.0
+        } else {
+            value
+        };
+        Number::from_f64(value).map_err(|_| CalcError::Domain(name))
+    };
+
+    match name {
+        "sqrt" => x.sqrt(),
+        "cbrt" => approx(f64::cbrt),
+        "abs" => Ok(match x {
+            Number::Exact(x) => Number::Exact(x.abs()),
+            Number::Approx(x) => Number::Approx(x.abs()),
+        }),
+        "floor" => Ok(x.round_with(BigRational::floor, f64::floor)),
+        "ceil" => Ok(x.round_with(BigRational::ceil, f64::ceil)),
+        "round" => Ok(x.round_with(BigRational::round, f64::round)),
+        "sin" => trig(f64::sin),
+        "cos" => trig(f64::cos),
+        "tan" => trig(f64::tan),
+        "asin" => approx(f64::asin),
+        "acos" => approx(f64::acos),
+        "atan" => approx(f64::atan),
+        "sinh" => approx(f64::sinh),
+        "cosh" => approx(f64::cosh),
+        "tanh" => approx(f64::tanh),
+        "ln" => x.log(std::f64::consts::E, name),
+        "log" => match args.get(1) {
+            Some(base) => x.log(base.to_f64(), name),
+            None => x.log(10.0, name),
+        },
+        "log2" => x.log(2.0, name),
+        "exp" => approx(f64::exp),
+        "min" => Ok(extreme(args, |a, b| b < a)),
+        "max" => Ok(extreme(args, |a, b| b > a)),
+        _ => Err(CalcError::UnknownName(name.to_string())),
+    }
+}
+
+// the argument `replace` prefers over all the others, keeping it exact if it was
+fn extreme(args: &[Number], replace: fn(f64, f64) -> bool) -> Number {
+    args.iter()
+        .cloned()
+        .reduce(|a, b| {
+            if replace(a.to_f64(), b.to_f64()) {
+                b
+            } else {
+                a
+            }
+        })
+        .unwrap()
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        Number::Exact(BigRational::from_integer(value.into()))
+    }
+}
+
+impl Number {
+    // infinities and nans can't be shown or calculated with further
+    pub fn from_f64(value: f64) -> Result<Self, CalcError> {
+        if value.is_nan() {
+            Err(CalcError::Domain("nan"))
+        } else if value.is_infinite() {
+            Err(CalcError::Overflow)
+        } else {
+            Ok(Number::Approx(value))
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(value) => value.to_f64().unwrap_or(f64::NAN),
+            Number::Approx(value) => *value,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Number::Exact(value) => value.is_zero(),
+            Number::Approx(value) => *value == 0.0,
+        }
+    }
+
+    fn binary(
+        &self,
+        other: &Number,
+        exact: impl FnOnce(&BigRational, &BigRational) -> BigRational,
+        approx: impl FnOnce(f64, f64) -> f64,
+    ) -> Result<Number, CalcError> {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Ok(Number::Exact(exact(a, b))),
+            (a, b) => Number::from_f64(approx(a.to_f64(), b.to_f64())),
+        }
+    }
+
+    pub fn add(&self, other: &Number) -> Result<Number, CalcError> {
+        self.binary(other, |a, b| a + b, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Number) -> Result<Number, CalcError> {
+        self.binary(other, |a, b| a - b, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Number) -> Result<Number, CalcError> {
+        self.binary(other, |a, b| a * b, |a, b| a * b)
+    }
+
+    pub fn div(&self, other: &Number) -> Result<Number, CalcError> {
+        if other.is_zero() {
+            return Err(CalcError::DivisionByZero);
+        }
+        self.binary(other, |a, b| a / b, |a, b| a / b)
+    }
+
+    // the remainder takes the sign of the divisor, so `-1 mod 3` is 2
+    pub fn rem(&self, other: &Number) -> Result<Number, CalcError> {
+        if other.is_zero() {
+            return Err(CalcError::DivisionByZero);
+        }
+        self.binary(
+            other,
+            |a, b| a - b * (a / b).floor(),
+            |a, b| a - b * (a / b).floor(),
+        )
+    }
+
+    pub fn pow(&self, exponent: &Number) -> Result<Number, CalcError> {
+        if let (Number::Exact(base), Number::Exact(exponent)) = (self, exponent)
+            && let Some(result) = exact_pow(base, exponent)?
+        {
+            return Ok(Number::Exact(result));
+        }
+        Number::from_f64(self.to_f64().powf(exponent.to_f64())).map_err(|err| match err {
+            CalcError::Domain(_) => CalcError::Domain("^"),
+            err => err,
+        })
+    }
+
+    pub fn sqrt(&self) -> Result<Number, CalcError> {
+        if let Number::Exact(value) = self {
+            if value.is_negative() {
+                return Err(CalcError::Domain("sqrt"));
+            }
+            let (numer, denom) = (value.numer().sqrt(), value.denom().sqrt());
+            if &numer * &numer == *value.numer() && &denom * &denom == *value.denom() {
+                return Ok(Number::Exact(BigRational::new(numer, denom)));
+            }
+        }
+        Number::from_f64(self.to_f64().sqrt()).map_err(|_| CalcError::Domain("sqrt"))
+    }
+
+    fn log(&self, base: f64, name: &'static str) -> Result<Number, CalcError> {
+        let value = self.to_f64();
+        if value <= 0.0 || base <= 0.0 || base == 1.0 {
+            return Err(CalcError::Domain(name));
+        }
+        let result = value.ln() / base.ln();
+        // keep exact powers of the base exact, e.g. log(1000) is 3 rather than 2.9999999999999996
+        let rounded = result.round();
+        if (result - rounded).abs() < 1e-12 && base.powf(rounded) == value {
+            return Ok(Number::from(rounded as i64));
+        }
+        Number::from_f64(result)
+    }
+
+    fn factorial(&self) -> Result<Number, CalcError> {
+        let Number::Exact(value) = self else {
+            return Err(CalcError::Domain("!"));
+        };
+        if !value.is_integer() || value.is_negative() {
+            return Err(CalcError::Domain("!"));
+        }
+        let n = value.to_integer().to_u64().ok_or(CalcError::Overflow)?;
+        if n > MAX_FACTORIAL {
+            return Err(CalcError::Overflow);
+        }
+        let result = (2..=n).fold(BigInt::one(), |acc, i| acc * i);
+        Ok(Number::Exact(BigRational::from_integer(result)))
+    }
+
+    fn round_with(&self, exact: fn(&BigRational) -> BigRational, approx: fn(f64) -> f64) -> Number {
+        match self {
+            Number::Exact(value) => Number::Exact(exact(value)),
+            Number::Approx(value) => Number::Approx(approx(*value)),
+        }
+    }
+}
+
+// `None` when the result would not be rational, or too large to be worth computing exactly
+fn exact_pow(base: &BigRational, exponent: &BigRational) -> Result<Option<BigRational>, CalcError> {
+    if !exponent.is_integer() {
+        return Ok(None);
+    }
+    let Some(exponent) = exponent.to_integer().to_i64() else {
+        return Ok(None);
+    };
+    if base.is_zero() {
+        return match exponent {
+            0 => Ok(Some(BigRational::one())),
+            exponent if exponent < 0 => Err(CalcError::DivisionByZero),
+            _ => Ok(Some(BigRational::zero())),
+        };
+    }
+
+    let bits = base.numer().bits().max(base.denom().bits());
+    if bits.saturating_mul(exponent.unsigned_abs()) > MAX_EXACT_POW_BITS {
+        return Ok(None);
+    }
+    let power = num_traits::pow(base.clone(), exponent.unsigned_abs() as usize);
+    Ok(Some(if exponent < 0 { power.recip() } else { power }))
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Exact(value) => write!(f, "{}", format_exact(value)),
+            Number::Approx(value) => write!(f, "{}", format_approx(*value)),
+        }
+    }
+}
+
+fn int_digits(value: &BigRational) -> usize {
+    value.abs().to_integer().to_string().len()
+}
+
+// whether `format_exact` writes out the value in full rather than rounding it
+fn shown_exactly(value: &BigRational) -> bool {
+    int_digits(value) <= MAX_INTEGER_DIGITS
+        && (value.is_integer()
+            || terminating_places(value.denom()).is_some_and(|places| places <= MAX_EXACT_DECIMALS))
+}
+
+// integers and short terminating decimals in full, everything else rounded
+fn format_exact(value: &BigRational) -> String {
+    let int_digits = int_digits(value);
+    if int_digits > MAX_INTEGER_DIGITS {
+        return scientific(value, int_digits as i64 - 1);
+    }
+    if shown_exactly(value) {
+        return match terminating_places(value.denom()) {
+            Some(places) if places > 0 => decimal(value, places),
+            _ => value.to_integer().to_string(),
+        };
+    }
+
+    let value_f64 = value.to_f64().unwrap_or(f64::NAN);
+    if value_f64.abs() < 1e-9 || value_f64.abs() >= 1e15 {
+        return format_approx(value_f64);
+    }
+    // enough places after the point for the significant digits, counting leading zeros
+    let magnitude = value_f64.abs().log10().floor() as i64;
+    let places = (SIGNIFICANT_DIGITS as i64 - 1 - magnitude).max(0) as usize;
+    decimal(value, places)
+}
+
+// `value` as a mantissa times ten to the `exponent`, which is adjusted if the mantissa rounds up
+fn scientific(value: &BigRational, exponent: i64) -> String {
+    let scale = BigRational::from_integer(num_traits::pow(BigInt::from(10), exponent as usize));
+    let mantissa = decimal(&(value / scale), SIGNIFICANT_DIGITS - 1);
+    if mantissa.trim_start_matches('-').starts_with("10") {
+        return scientific(value, exponent + 1);
+    }
+    format!("{mantissa}e{exponent}")
+}
+
+// the number of decimal places a fraction with this denominator needs, if it terminates
+fn terminating_places(denom: &BigInt) -> Option<usize> {
+    let (mut denom, mut twos, mut fives) = (denom.clone(), 0, 0);
+    let (two, five) = (BigInt::from(2), BigInt::from(5));
+    while denom.is_even() {
+        denom /= &two;
+        twos += 1;
+    }
+    while (&denom % &five).is_zero() {
+        denom /= &five;
+        fives += 1;
+    }
+    denom.is_one().then_some(twos.max(fives))
+}
+
+// rounds half away from zero to `places` decimals, dropping trailing zeros
+fn decimal(value: &BigRational, places: usize) -> String {
+    let scale = num_traits::pow(BigInt::from(10), places);
+    let scaled = (value.abs() * BigRational::from_integer(scale))
+        .round()
+        .to_integer();
+    let digits = format!("{:0>width$}", scaled.to_string(), width = places + 1);
+    let (int, frac) = digits.split_at(digits.len() - places);
+    let frac = frac.trim_end_matches('0');
+    let sign = if value.is_negative() && !scaled.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    if frac.is_empty() {
+        format!("{sign}{int}")
+    } else {
+        format!("{sign}{int}.{frac}")
+    }
+}
+
+fn format_approx(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let abs = value.abs();
+    if !(1e-9..1e15).contains(&abs) {
+        // scientific notation, with the mantissa's trailing zeros dropped
+        let text = format!("{:.*e}", SIGNIFICANT_DIGITS - 1, value);
+        let (mantissa, exponent) = text.split_once('e').unwrap();
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        return format!("{mantissa}e{exponent}");
+    }
+    let magnitude = abs.log10().floor() as i64;
+    let places = (SIGNIFICANT_DIGITS as i64 - 1 - magnitude).max(0) as usize;
+    let text = format!("{value:.places$}");
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &text
+    };
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+// answers the query with its value when it's arithmetic
+#[derive(Default)]
+pub struct CalcAnswerer {
+    // in the calculator mode a bare number is still worth showing
+    allow_trivial: bool,
+}
+
+impl CalcAnswerer {
+    pub fn with_trivial(mut self, allow_trivial: bool) -> Self {
+        self.allow_trivial = allow_trivial;
+        self
+    }
+}
+
+impl Answerer for CalcAnswerer {
+    fn answer(&self, query: &str) -> Vec<Answer> {
+        let query = query.trim().trim_start_matches('=').trim_end_matches('=');
+        let Ok(expr) = parse(query) else {
+            return Vec::new();
+        };
+        if expr.is_trivial() && !self.allow_trivial {
+            return Vec::new();
+        }
+        let Ok(value) = expr.eval() else {
+            return Vec::new();
+        };
+
+        let query = query.trim();
+        let detail = match &value {
+            Number::Exact(ratio) if shown_exactly(ratio) => format!("{query} ="),
+            // repeating fractions are rounded for display, so show the exact value alongside
+            Number::Exact(ratio) => {
+                let fraction = ratio.to_string();
+                if fraction.len() <= MAX_INTEGER_DIGITS && fraction != query.replace(' ', "") {
+                    format!("{query} = {fraction}")
+                } else {
+                    format!("{query} ≈")
+                }
+            }
+            Number::Approx(_) => format!("{query} ≈"),
+        };
+        vec![Answer {
+            value: value.to_string(),
+            detail,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> String {
+        evaluate(input).unwrap().to_string()
+    }
+
+    fn error(input: &str) -> CalcError {
+        evaluate(input).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("2 ^ 3 ^ 2"), "512");
+        assert_eq!(eval("2**10"), "1024");
+        assert_eq!(eval("7 mod 3 + 1"), "2");
+        assert_eq!(eval("3 × 4 ÷ 2"), "6");
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(eval("-2^2"), "-4");
+        assert_eq!(eval("-(-3)"), "3");
+        assert_eq!(eval("2^-1"), "0.5");
+        assert_eq!(eval("-1 mod 3"), "2");
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!(eval("2pi"), "6.28318530718");
+        assert_eq!(eval("3(1+2)"), "9");
+        assert_eq!(eval("(1+2)(3+4)"), "21");
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(eval("200 + 10%"), "220");
+        assert_eq!(eval("200 - 10%"), "180");
+        assert_eq!(eval("50%"), "0.5");
+        assert_eq!(eval("10% * 200"), "20");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval("0xff"), "255");
+        assert_eq!(eval("0b1010 + 1"), "11");
+        assert_eq!(eval("0o17"), "15");
+        assert_eq!(eval("1_000 * 3"), "3000");
+        assert_eq!(eval("1.5e-3"), "0.0015");
+        assert_eq!(eval(".5"), "0.5");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("sqrt(16)"), "4");
+        assert_eq!(eval("sqrt(2)"), "1.41421356237");
+        assert_eq!(eval("sin(pi)"), "0");
+        assert_eq!(eval("sin(30deg)"), "0.5");
+        assert_eq!(eval("log(1000)"), "3");
+        assert_eq!(eval("log(8, 2)"), "3");
+        assert_eq!(eval("ln(e)"), "1");
+        assert_eq!(eval("abs(-3.5)"), "3.5");
+        assert_eq!(eval("floor(2.7)"), "2");
+        assert_eq!(eval("ceil(-2.7)"), "-2");
+        assert_eq!(eval("round(2.5)"), "3");
+        assert_eq!(eval("min(3, 1, 2)"), "1");
+        assert_eq!(eval("max(3, 1/2)"), "3");
+        assert_eq!(eval("5!"), "120");
+        assert_eq!(
+            error("sin(1, 2)"),
+            CalcError::Arity {
+                name: "sin".to_string(),
+                expected: "one argument"
+            }
+        );
+        assert_eq!(error("foo"), CalcError::UnknownName("foo".to_string()));
+    }
+
+    #[test]
+    fn exactness() {
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("1.1 * 1.1"), "1.21");
+        assert_eq!(eval("1/3"), "0.333333333333");
+        assert_eq!(eval("10^20"), "100000000000000000000");
+        assert_eq!(eval("123456789 * 987654321"), "121932631112635269");
+        assert!(matches!(evaluate("1/3 * 3"), Ok(Number::Exact(_))));
+        assert!(matches!(evaluate("sqrt(9/4)"), Ok(Number::Exact(_))));
+        assert!(matches!(evaluate("sqrt(2)"), Ok(Number::Approx(_))));
+        assert!(matches!(evaluate("2^0.5"), Ok(Number::Approx(_))));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("1/0"), CalcError::DivisionByZero);
+        assert_eq!(error("1 mod 0"), CalcError::DivisionByZero);
+        assert_eq!(error("0^-1"), CalcError::DivisionByZero);
+        assert_eq!(error("sqrt(-1)"), CalcError::Domain("sqrt"));
+        assert_eq!(error("ln(0)"), CalcError::Domain("ln"));
+        assert_eq!(error("10^1000.5"), CalcError::Overflow);
+        assert_eq!(error("100000!"), CalcError::Overflow);
+        assert!(matches!(error("(1+2"), CalcError::Syntax { at: 4, .. }));
+        assert!(matches!(error("2 3"), CalcError::Syntax { at: 2, .. }));
+    }
+
+    #[test]
+    fn trivial_queries_arent_answered() {
+        let answerer = CalcAnswerer::default();
+        assert!(answerer.answer("42").is_empty());
+        assert!(answerer.answer("pi").is_empty());
+        assert!(answerer.answer("firefox").is_empty());
+        assert_eq!(answerer.answer("0xff")[0].value, "255");
+        assert_eq!(
+            CalcAnswerer::default().with_trivial(true).answer("42")[0].value,
+            "42"
+        );
+    }
+
+    #[test]
+    fn repeating_fractions_show_the_fraction() {
+        let answer = &CalcAnswerer::default().answer("1/3 + 1/3")[0];
+        assert_eq!(answer.value, "0.666666666667");
+        assert_eq!(answer.detail, "1/3 + 1/3 = 2/3");
+        let answer = &CalcAnswerer::default().answer("1 + 2")[0];
+        assert_eq!(answer.detail, "1 + 2 =");
+    }
+}
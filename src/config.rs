@@ -0,0 +1,683 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use gpui::{Global, Keystroke};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+
+use crate::{
+    clipboard::{ClipboardHistory, Filter},
+    indexer::Indexer,
+    mode::Mode,
+    web,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hotkeys: HotkeysConfig,
+    pub panel: PanelConfig,
+    pub input: InputConfig,
+    pub keys: KeysConfig,
+    pub search: SearchConfig,
+    pub files: FilesConfig,
+    pub web: WebConfig,
+    pub shell: ShellConfig,
+    pub clipboard: ClipboardConfig,
+    pub storage: StorageConfig,
+}
+
+impl Global for Config {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HotkeyBinding {
+    // as written in the config, for error messages
+    pub chord: String,
+    pub hotkey: HotKey,
+    pub mode: Mode,
+}
+
+// chords mapped to the mode they open the panel in, e.g. `"alt+space" = "search"`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "BTreeMap<String, Mode>")]
+pub struct HotkeysConfig {
+    pub bindings: Vec<HotkeyBinding>,
+    // the first chord that didn't parse or repeats another, reported by `Config::validate`
+    error: Option<String>,
+}
+
+impl Default for HotkeysConfig {
+    fn default() -> Self {
+        Self {
+            bindings: vec![HotkeyBinding {
+                chord: "alt+space".to_string(),
+                hotkey: HotKey::new(Some(Modifiers::ALT), Code::Space),
+                mode: Mode::Search,
+            }],
+            error: None,
+        }
+    }
+}
+
+impl From<BTreeMap<String, Mode>> for HotkeysConfig {
+    fn from(table: BTreeMap<String, Mode>) -> Self {
+        let mut bindings: Vec<HotkeyBinding> = Vec::new();
+        let mut error = None;
+        for (chord, mode) in table {
+            let hotkey: HotKey = match chord.parse() {
+                Ok(hotkey) => hotkey,
+                Err(err) => {
+                    error.get_or_insert(format!("invalid hotkey \"{chord}\": {err}"));
+                    continue;
+                }
+            };
+            // different spellings of the same chord, e.g. "alt" and "option"
+            if let Some(other) = bindings.iter().find(|binding| binding.hotkey == hotkey) {
+                error.get_or_insert(format!(
+                    "hotkey \"{chord}\" is the same chord as \"{}\"",
+                    other.chord
+                ));
+                continue;
+            }
+            bindings.push(HotkeyBinding {
+                chord,
+                hotkey,
+                mode,
+            });
+        }
+        Self { bindings, error }
+    }
+}
+
+impl HotkeysConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        match &self.error {
+            Some(message) => Err(ConfigError::Invalid {
+                key: "hotkeys",
+                message: message.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PanelConfig {
+    pub width: f32,
+    pub height: f32,
+    pub expanded_height: f32,
+    // distance of the panel from the top of the display, as a fraction of its height
+    pub vertical_offset: f32,
+    // only show the panel while a hotkey is held down, instead of toggling on each press
+    pub hold_to_show: bool,
+}
+
+impl Default for PanelConfig {
+    fn default() -> Self {
+        Self {
+            width: 650.0,
+            height: 75.0,
+            expanded_height: 300.0,
+            vertical_offset: 0.2,
+            hold_to_show: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub placeholder: String,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            placeholder: "Halcyon".to_string(),
+        }
+    }
+}
+
+// keystrokes in gpui's syntax, e.g. "ctrl-n" or "cmd-enter"
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub select_next: Vec<String>,
+    pub select_prev: Vec<String>,
+    pub open: Vec<String>,
+    pub reveal: Vec<String>,
+    pub copy_path: Vec<String>,
+    // removes the selected clipboard history entry
+    pub delete: Vec<String>,
+    pub hide: Vec<String>,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        Self {
+            select_next: keys(&["down", "ctrl-n"]),
+            select_prev: keys(&["up", "ctrl-p"]),
+            open: keys(&["enter"]),
+            reveal: keys(&["cmd-enter"]),
+            copy_path: keys(&["alt-enter"]),
+            delete: keys(&["cmd-backspace"]),
+            hide: keys(&["escape"]),
+        }
+    }
+}
+
+impl KeysConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let all = [
+            ("keys.select_next", &self.select_next),
+            ("keys.select_prev", &self.select_prev),
+            ("keys.open", &self.open),
+            ("keys.reveal", &self.reveal),
+            ("keys.copy_path", &self.copy_path),
+            ("keys.delete", &self.delete),
+            ("keys.hide", &self.hide),
+        ];
+        for (key, bindings) in all {
+            for binding in bindings {
+                for keystroke in binding.split_whitespace() {
+                    Keystroke::parse(keystroke).map_err(|err| ConfigError::Invalid {
+                        key,
+                        message: format!("\"{binding}\" is not a valid keystroke: {err}"),
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub spotlight: bool,
+    pub index_roots: Vec<PathBuf>,
+    pub debounce_ms: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            spotlight: true,
+            index_roots: Indexer::default_roots(),
+            debounce_ms: 40,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    // typed first in search, they switch it to searching files
+    pub prefixes: Vec<String>,
+    // walked when spotlight is off, `in:` overrides them
+    pub roots: Vec<PathBuf>,
+    pub max_depth: usize,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        let home = dirs::home_dir().unwrap_or_default();
+        Self {
+            prefixes: vec!["'".to_string(), "/".to_string()],
+            roots: vec![
+                home.join("Desktop"),
+                home.join("Documents"),
+                home.join("Downloads"),
+            ],
+            max_depth: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebConfig {
+    // keywords mapped to url templates, `gh halcyon` opens the template with `{query}`
+    // replaced by "halcyon"
+    pub quicklinks: BTreeMap<String, String>,
+    // offered when nothing matches a search, empty turns it off
+    pub fallback: String,
+    // offer to open searches that look like addresses
+    pub detect_urls: bool,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            quicklinks: BTreeMap::from([
+                (
+                    "g".to_string(),
+                    "https://www.google.com/search?q={query}".to_string(),
+                ),
+                (
+                    "gh".to_string(),
+                    "https://github.com/search?q={query}".to_string(),
+                ),
+            ]),
+            fallback: "https://www.google.com/search?q={query}".to_string(),
+            detect_urls: true,
+        }
+    }
+}
+
+impl WebConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        for (keyword, template) in &self.quicklinks {
+            if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+                return Err(ConfigError::Invalid {
+                    key: "web.quicklinks",
+                    message: format!("\"{keyword}\" can't be empty or contain whitespace"),
+                });
+            }
+            web::expand(template, "").map_err(|err| ConfigError::Invalid {
+                key: "web.quicklinks",
+                message: format!("\"{keyword}\": {err}"),
+            })?;
+        }
+        if !self.fallback.is_empty() {
+            web::expand(&self.fallback, "").map_err(|err| ConfigError::Invalid {
+                key: "web.fallback",
+                message: err.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShellConfig {
+    // typed first in search, it runs the rest as a shell command
+    pub prefix: String,
+    // empty means the login shell from $SHELL
+    pub program: PathBuf,
+    // where commands run
+    pub cwd: PathBuf,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            prefix: ">".to_string(),
+            program: PathBuf::new(),
+            cwd: dirs::home_dir().unwrap_or_else(|| PathBuf::from("/")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    // record everything copied, for the clipboard panel mode
+    pub enabled: bool,
+    // older entries are dropped
+    pub max_entries: usize,
+    // entries are dropped after this many days, 0 keeps them
+    pub max_age_days: u32,
+    // bundle ids of applications whose copies are never recorded
+    pub ignore_apps: Vec<String>,
+    // text matching any of these regexes is never recorded, e.g. api keys
+    pub ignore_patterns: Vec<String>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: ClipboardHistory::MAX_ENTRIES,
+            max_age_days: ClipboardHistory::MAX_AGE_DAYS,
+            ignore_apps: vec![
+                "com.apple.keychainaccess".to_string(),
+                "com.apple.Passwords".to_string(),
+            ],
+            ignore_patterns: vec![
+                // private keys, and tokens from openai, github, slack and aws
+                "-----BEGIN [A-Z ]*PRIVATE KEY-----".to_string(),
+                r"\bsk-[A-Za-z0-9_-]{20,}".to_string(),
+                r"\bgh[pousr]_[A-Za-z0-9]{36,}".to_string(),
+                r"\bxox[abprs]-[A-Za-z0-9-]{10,}".to_string(),
+                r"\bAKIA[0-9A-Z]{16}\b".to_string(),
+            ],
+        }
+    }
+}
+
+// read once at startup, files are migrated as they're next read
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    // encrypt launch and clipboard history
+    pub encrypt: bool,
+    pub key: KeyKind,
+    // used with `key = "file"`
+    pub key_file: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyKind {
+    // a random key in the login keychain
+    Keychain,
+    // a random key in `key_file`
+    File,
+    // from $HALCYON_PASSPHRASE
+    Passphrase,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            encrypt: false,
+            key: if cfg!(target_os = "macos") {
+                KeyKind::Keychain
+            } else {
+                KeyKind::File
+            },
+            key_file: dirs::data_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("halcyon")
+                .join("storage.key"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    // toml errors already carry the line, column and key
+    Parse(toml::de::Error),
+    Invalid { key: &'static str, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            ConfigError::Parse(err) => write!(f, "invalid config: {err}"),
+            ConfigError::Invalid { key, message } => {
+                write!(f, "invalid value for `{key}`: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // $XDG_CONFIG_HOME/halcyon/config.toml, falling back to ~/.config
+    pub fn path() -> PathBuf {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .unwrap_or_default()
+            .join("halcyon")
+            .join("config.toml")
+    }
+
+    // a missing file is not an error, it just means the defaults
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError::Io(path.to_path_buf(), err)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut config: Config = toml::from_str(text).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        config.search.index_roots = config
+            .search
+            .index_roots
+            .iter()
+            .map(|root| expand_home(root))
+            .collect();
+        config.files.roots = config
+            .files
+            .roots
+            .iter()
+            .map(|root| expand_home(root))
+            .collect();
+        config.shell.program = expand_home(&config.shell.program);
+        config.shell.cwd = expand_home(&config.shell.cwd);
+        config.storage.key_file = expand_home(&config.storage.key_file);
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.hotkeys.validate()?;
+        let panel = &self.panel;
+        positive("panel.width", panel.width)?;
+        positive("panel.height", panel.height)?;
+        positive("panel.expanded_height", panel.expanded_height)?;
+        if panel.expanded_height < panel.height {
+            return Err(ConfigError::Invalid {
+                key: "panel.expanded_height",
+                message: format!(
+                    "must be at least `panel.height` ({}), got {}",
+                    panel.height, panel.expanded_height
+                ),
+            });
+        }
+        if !(0.0..=1.0).contains(&panel.vertical_offset) {
+            return Err(ConfigError::Invalid {
+                key: "panel.vertical_offset",
+                message: format!("must be between 0 and 1, got {}", panel.vertical_offset),
+            });
+        }
+        if self
+            .files
+            .prefixes
+            .iter()
+            .any(|prefix| prefix.trim().is_empty())
+        {
+            return Err(ConfigError::Invalid {
+                key: "files.prefixes",
+                message: "prefixes can't be empty or whitespace".to_string(),
+            });
+        }
+        if self.files.max_depth == 0 {
+            return Err(ConfigError::Invalid {
+                key: "files.max_depth",
+                message: "must be at least 1".to_string(),
+            });
+        }
+        self.web.validate()?;
+        if self.shell.prefix.trim().is_empty() {
+            return Err(ConfigError::Invalid {
+                key: "shell.prefix",
+                message: "can't be empty or whitespace".to_string(),
+            });
+        }
+        Filter::new(&self.clipboard).map_err(|err| ConfigError::Invalid {
+            key: "clipboard.ignore_patterns",
+            message: err.to_string(),
+        })?;
+        self.keys.validate()
+    }
+
+    // the watcher has to stay alive for changes to keep coming, editors tend to replace the
+    // file rather than write to it so the whole directory is watched
+    pub fn watch(path: &Path) -> notify::Result<(RecommendedWatcher, UnboundedReceiver<()>)> {
+        let (tx, rx) = mpsc::unbounded();
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            if event
+                .paths
+                .iter()
+                .any(|path| path.file_name().map(|name| name.to_os_string()) == file_name)
+            {
+                let _ = tx.unbounded_send(());
+            }
+        })?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok((watcher, rx))
+    }
+}
+
+fn positive(key: &'static str, value: f32) -> Result<(), ConfigError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid {
+            key,
+            message: format!("must be a positive number, got {value}"),
+        })
+    }
+}
+
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_key(text: &str) -> &'static str {
+        match Config::parse(text) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid value, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_file_is_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn partial_tables_keep_the_other_defaults() {
+        let config = Config::parse("[panel]\nwidth = 800\n\n[shell]\nprefix = \"$\"\n").unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.panel.width, 800.0);
+        assert_eq!(config.panel.height, defaults.panel.height);
+        assert_eq!(config.shell.prefix, "$");
+        assert_eq!(config.shell.cwd, defaults.shell.cwd);
+        assert_eq!(config.hotkeys, defaults.hotkeys);
+        assert_eq!(config.clipboard, defaults.clipboard);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(matches!(
+            Config::parse("[panel]\ncolour = \"red\"\n"),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            Config::parse("[themes]\n"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn hotkeys() {
+        let config = Config::parse("[hotkeys]\n\"cmd+shift+v\" = \"clipboard\"\n").unwrap();
+        assert_eq!(config.hotkeys.bindings.len(), 1);
+        assert_eq!(config.hotkeys.bindings[0].chord, "cmd+shift+v");
+        assert_eq!(config.hotkeys.bindings[0].mode, Mode::Clipboard);
+
+        assert_eq!(
+            invalid_key("[hotkeys]\n\"hyper+x\" = \"search\"\n"),
+            "hotkeys"
+        );
+        // the same chord spelled two ways
+        let duplicate = "[hotkeys]\n\"alt+space\" = \"search\"\n\"option+space\" = \"clipboard\"\n";
+        match Config::parse(duplicate) {
+            Err(ConfigError::Invalid { key, message }) => {
+                assert_eq!(key, "hotkeys");
+                assert!(message.contains("\"option+space\""), "{message}");
+                assert!(message.contains("\"alt+space\""), "{message}");
+            }
+            other => panic!("expected a duplicate chord, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn duplicate_chords() {
+        let table = |chords: &[&str]| {
+            HotkeysConfig::from(
+                chords
+                    .iter()
+                    .map(|chord| (chord.to_string(), Mode::Search))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+        };
+        let hotkeys = table(&["alt+space", "cmd+space", "ctrl+alt+space"]);
+        assert_eq!(hotkeys.bindings.len(), 3);
+        assert!(hotkeys.validate().is_ok());
+
+        // modifiers in any order or spelling, and keys in any case, are the same chord
+        for duplicate in [
+            ["alt+shift+k", "shift+alt+k"],
+            ["alt+space", "option+space"],
+            ["cmd+k", "super+K"],
+        ] {
+            let hotkeys = table(&duplicate);
+            assert_eq!(hotkeys.bindings.len(), 1, "{duplicate:?}");
+            assert!(
+                matches!(
+                    hotkeys.validate(),
+                    Err(ConfigError::Invalid { key: "hotkeys", .. })
+                ),
+                "{duplicate:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        assert_eq!(invalid_key("[panel]\nwidth = -1\n"), "panel.width");
+        assert_eq!(invalid_key("[panel]\nheight = 0\n"), "panel.height");
+        assert_eq!(
+            invalid_key("[panel]\nexpanded_height = -300\n"),
+            "panel.expanded_height"
+        );
+        assert_eq!(
+            invalid_key("[panel]\nheight = 400\nexpanded_height = 300\n"),
+            "panel.expanded_height"
+        );
+        assert_eq!(
+            invalid_key("[panel]\nvertical_offset = 1.5\n"),
+            "panel.vertical_offset"
+        );
+        assert_eq!(
+            invalid_key("[clipboard]\nignore_patterns = [\"(unclosed\"]\n"),
+            "clipboard.ignore_patterns"
+        );
+        assert_eq!(invalid_key("[keys]\nopen = [\"ctrl-a-b\"]\n"), "keys.open");
+        assert_eq!(invalid_key("[files]\nmax_depth = 0\n"), "files.max_depth");
+        assert_eq!(invalid_key("[shell]\nprefix = \" \"\n"), "shell.prefix");
+        assert_eq!(
+            invalid_key("[web]\nquicklinks = { \"g h\" = \"https://x/{query}\" }\n"),
+            "web.quicklinks"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Duration, Month, Months, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use chrono_tz::{OffsetName, TZ_VARIANTS, Tz};
+
+use crate::search::{Answer, Answerer};
+
+// injected so answers can be checked against a known instant
+pub trait Clock: Send {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// always the same instant
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+// the system's zone, falling back to utc when it isn't a known iana name
+pub fn local_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateQuery {
+    // "now", "unix 1700000000", "time in tokyo", "now + 3 hours"
+    Instant { at: DateTime<Utc>, zone: Tz },
+    // "today", "2026-10-17 + 45 days"
+    Date(NaiveDate),
+    // "days until dec 25", "days since 2020-01-01", always counted from `from` to `to`
+    DaysBetween { from: NaiveDate, to: NaiveDate },
+    // "week number", "week of 2026-01-01"
+    Week(NaiveDate),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Span {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl Span {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "s" | "sec" | "secs" | "second" | "seconds" => Span::Seconds,
+            "min" | "mins" | "minute" | "minutes" => Span::Minutes,
+            "h" | "hr" | "hrs" | "hour" | "hours" => Span::Hours,
+            "d" | "day" | "days" => Span::Days,
+            "w" | "wk" | "wks" | "week" | "weeks" => Span::Weeks,
+            "mo" | "month" | "months" => Span::Months,
+            "y" | "yr" | "yrs" | "year" | "years" => Span::Years,
+            _ => return None,
+        })
+    }
+
+    fn is_calendar(self) -> bool {
+        matches!(self, Span::Days | Span::Weeks | Span::Months | Span::Years)
+    }
+}
+
+// a point to count from, either a whole day or an exact instant
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Base {
+    Date(NaiveDate),
+    Instant(DateTime<Utc>),
+}
+
+// `now` resolves relative words like "today" and years left out of "dec 25"
+pub fn parse(query: &str, now: DateTime<Utc>, local: Tz) -> Option<DateQuery> {
+    let query = query.trim().to_lowercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let today = now.with_timezone(&local).date_naive();
+
+    match words.as_slice() {
+        ["now" | "time" | "date" | "unix" | "timestamp"] | ["unix", "time"] => {
+            Some(DateQuery::Instant {
+                at: now,
+                zone: local,
+            })
+        }
+        ["unix" | "timestamp", stamp] | [stamp, "unix"] => Some(DateQuery::Instant {
+            at: timestamp(stamp)?,
+            zone: local,
+        }),
+        [stamp] if stamp.starts_with('@') => Some(DateQuery::Instant {
+            at: timestamp(&stamp[1..])?,
+            zone: local,
+        }),
+        ["time" | "now", "in", zone @ ..] | [zone @ .., "time"] if !zone.is_empty() => {
+            Some(DateQuery::Instant {
+                at: now,
+                zone: find_zone(&zone.join(" "))?,
+            })
+        }
+        [
+            "days",
+            direction @ ("until" | "till" | "to" | "since"),
+            date @ ..,
+        ] => {
+            let future = *direction != "since";
+            let date = date_words(date, today, Some(future))?;
+            let (from, to) = if future { (today, date) } else { (date, today) };
+            Some(DateQuery::DaysBetween { from, to })
+        }
+        ["week"] | ["week", "number"] => Some(DateQuery::Week(today)),
+        ["week", "of" | "for", date @ ..] | ["week", "number", "of" | "for", date @ ..] => {
+            Some(DateQuery::Week(date_words(date, today, None)?))
+        }
+        _ => shift(&words, now, local, today),
+    }
+}
+
+// "<base>", "<base> + 45 days", "<base> - 2 weeks", where a bare base must be a date word so
+// plain numbers are left to the calculator
+fn shift(words: &[&str], now: DateTime<Utc>, local: Tz, today: NaiveDate) -> Option<DateQuery> {
+    let op = words.iter().position(|word| *word == "+" || *word == "-");
+    let (base_words, rest) = match op {
+        Some(idx) => (&words[..idx], &words[idx..]),
+        None => (words, &[][..]),
+    };
+
+    let base = match base_words {
+        ["now"] => Base::Instant(now),
+        words => match parse_datetime(&words.join(" ")) {
+            Some(at) => Base::Instant(local.from_local_datetime(&at).earliest()?.to_utc()),
+            None => Base::Date(date_words(words, today, None)?),
+        },
+    };
+
+    let base = match rest {
+        [] => base,
+        [op, amount, span] => {
+            let amount: i64 = amount.parse().ok()?;
+            let amount = if *op == "-" { -amount } else { amount };
+            add(base, amount, Span::parse(span)?, local)?
+        }
+        // "+ 45days"
+        [op, amount_span] => {
+            let split = amount_span.find(|c: char| !c.is_ascii_digit())?;
+            let amount: i64 = amount_span[..split].parse().ok()?;
+            let amount = if *op == "-" { -amount } else { amount };
+            add(base, amount, Span::parse(&amount_span[split..])?, local)?
+        }
+        _ => return None,
+    };
+
+    Some(match base {
+        Base::Date(date) => DateQuery::Date(date),
+        Base::Instant(at) => DateQuery::Instant { at, zone: local },
+    })
+}
+
+fn add(base: Base, amount: i64, span: Span, local: Tz) -> Option<Base> {
+    match (base, span) {
+        (Base::Date(date), span) if span.is_calendar() => {
+            Some(Base::Date(add_calendar(date, amount, span)?))
+        }
+        // hours from a date count from its midnight
+        (Base::Date(date), span) => {
+            let midnight = local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()?
+                .to_utc();
+            add(Base::Instant(midnight), amount, span, local)
+        }
+        (Base::Instant(at), Span::Seconds) => {
+            Some(Base::Instant(at + Duration::try_seconds(amount)?))
+        }
+        (Base::Instant(at), Span::Minutes) => {
+            Some(Base::Instant(at + Duration::try_minutes(amount)?))
+        }
+        (Base::Instant(at), Span::Hours) => Some(Base::Instant(at + Duration::try_hours(amount)?)),
+        // calendar spans move the local date and keep the wall clock time
+        (Base::Instant(at), span) => {
+            let local_at = at.with_timezone(&local).naive_local();
+            let date = add_calendar(local_at.date(), amount, span)?;
+            let shifted = local
+                .from_local_datetime(&date.and_time(local_at.time()))
+                .earliest()?;
+            Some(Base::Instant(shifted.to_utc()))
+        }
+    }
+}
+
+fn add_calendar(date: NaiveDate, amount: i64, span: Span) -> Option<NaiveDate> {
+    match span {
+        Span::Days => date.checked_add_signed(Duration::try_days(amount)?),
+        Span::Weeks => date.checked_add_signed(Duration::try_weeks(amount)?),
+        Span::Months | Span::Years => {
+            let months = if span == Span::Years {
+                amount.checked_mul(12)?
+            } else {
+                amount
+            };
+            let magnitude = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+            if months < 0 {
+                date.checked_sub_months(magnitude)
+            } else {
+                date.checked_add_months(magnitude)
+            }
+        }
+        _ => None,
+    }
+}
+
+// seconds, or milliseconds when there are too many digits for seconds to be plausible
+fn timestamp(stamp: &str) -> Option<DateTime<Utc>> {
+    let stamp: i64 = stamp.parse().ok()?;
+    if stamp.unsigned_abs() >= 100_000_000_000 {
+        DateTime::from_timestamp_millis(stamp)
+    } else {
+        DateTime::from_timestamp(stamp, 0)
+    }
+}
+
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+}
+
+// "today", "2026-10-17", "dec 25", "25 december 2027", ... a date without a year is the next
+// occurrence when `future` is set, the last one when it's unset, and this year's otherwise
+fn date_words(words: &[&str], today: NaiveDate, future: Option<bool>) -> Option<NaiveDate> {
+    let (month, day, year) = match words {
+        ["today"] => return Some(today),
+        ["tomorrow"] => return today.succ_opt(),
+        ["yesterday"] => return today.pred_opt(),
+        [date] => return NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        [a, b] => month_day(a, b)?,
+        [a, b, year] => {
+            let (month, day, _) = month_day(a, b)?;
+            (month, day, Some(year.parse().ok()?))
+        }
+        _ => return None,
+    };
+
+    let date = |year: i32| NaiveDate::from_ymd_opt(year, month, day);
+    match (year, future) {
+        (Some(year), _) => date(year),
+        (None, Some(true)) => date(today.year())
+            .filter(|date| *date >= today)
+            .or_else(|| date(today.year() + 1)),
+        (None, Some(false)) => date(today.year())
+            .filter(|date| *date <= today)
+            .or_else(|| date(today.year() - 1)),
+        (None, None) => date(today.year()),
+    }
+}
+
+// "dec 25" or "25 dec", with an optional ordinal suffix on the day
+fn month_day(a: &str, b: &str) -> Option<(u32, u32, Option<i32>)> {
+    let day = |word: &str| -> Option<u32> {
+        word.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '.')
+            .parse()
+            .ok()
+    };
+    let month = |word: &str| Month::from_str(word.trim_end_matches('.')).ok();
+
+    let (month, day) = match (month(a), month(b)) {
+        (Some(month), None) => (month, day(b)?),
+        (None, Some(month)) => (month, day(a)?),
+        _ => return None,
+    };
+    Some((month.number_from_month(), day, None))
+}
+
+// an iana name ("america/new_york"), its city ("new york") or an abbreviation like "utc"
+pub fn find_zone(name: &str) -> Option<Tz> {
+    let name = name.trim().replace(' ', "_");
+    TZ_VARIANTS.iter().copied().find(|zone| {
+        let zone_name = zone.name();
+        zone_name.eq_ignore_ascii_case(&name)
+            || zone_name
+                .rsplit('/')
+                .next()
+                .is_some_and(|city| city.eq_ignore_ascii_case(&name))
+    })
+}
+
+impl DateQuery {
+    pub fn answers(&self) -> Vec<Answer> {
+        match self {
+            DateQuery::Instant { at, zone } => {
+                let local = at.with_timezone(zone);
+                let abbreviation = local.offset().abbreviation().unwrap_or("");
+                let zone = format!(
+                    "{} ({abbreviation}, UTC{})",
+                    zone.name(),
+                    local.format("%:z")
+                );
+                let mut answers = vec![
+                    Answer {
+                        value: local.format("%A, %-d %B %Y %H:%M:%S").to_string(),
+                        detail: zone,
+                    },
+                    Answer {
+                        value: local.to_rfc3339(),
+                        detail: "ISO 8601 / RFC 3339".to_string(),
+                    },
+                ];
+                // rfc 2822 only has four digit years, chrono panics formatting any other
+                if (0..=9999).contains(&local.year()) {
+                    answers.push(Answer {
+                        value: local.to_rfc2822(),
+                        detail: "RFC 2822".to_string(),
+                    });
+                }
+                answers.push(Answer {
+                    value: at.timestamp().to_string(),
+                    detail: "unix timestamp".to_string(),
+                });
+                answers
+            }
+            DateQuery::Date(date) => vec![Answer {
+                value: date.format("%Y-%m-%d").to_string(),
+                detail: format!(
+                    "{}, week {}",
+                    date.format("%A, %-d %B %Y"),
+                    date.iso_week().week()
+                ),
+            }],
+            DateQuery::DaysBetween { from, to } => {
+                let days = (*to - *from).num_days();
+                vec![Answer {
+                    value: days.to_string(),
+                    detail: format!(
+                        "days from {} to {}",
+                        from.format("%a %-d %b %Y"),
+                        to.format("%a %-d %b %Y")
+                    ),
+                }]
+            }
+            DateQuery::Week(date) => {
+                let week = date.iso_week();
+                vec![Answer {
+                    value: week.week().to_string(),
+                    detail: format!(
+                        "ISO week of {} ({}-W{:02})",
+                        date.format("%Y-%m-%d"),
+                        week.year(),
+                        week.week()
+                    ),
+                }]
+            }
+        }
+    }
+}
+
+pub struct DateAnswerer {
+    clock: Box<dyn Clock>,
+    local: Tz,
+}
+
+impl DateAnswerer {
+    pub fn new(clock: impl Clock + 'static, local: Tz) -> Self {
+        Self {
+            clock: Box::new(clock),
+            local,
+        }
+    }
+
+    pub fn system() -> Self {
+        Self::new(SystemClock, local_zone())
+    }
+}
+
+impl Answerer for DateAnswerer {
+    fn answer(&self, query: &str) -> Vec<Answer> {
+        parse(query, self.clock.now(), self.local)
+            .map(|query| query.answers())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a saturday, in summer time in berlin
+    fn answer(query: &str) -> Vec<Answer> {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 30, 0).unwrap();
+        DateAnswerer::new(FixedClock(now), Tz::Europe__Berlin).answer(query)
+    }
+
+    fn values(query: &str) -> Vec<String> {
+        answer(query)
+            .into_iter()
+            .map(|answer| answer.value)
+            .collect()
+    }
+
+    #[test]
+    fn now_in_every_format() {
+        assert_eq!(
+            values("now"),
+            [
+                "Saturday, 17 October 2026 14:30:00",
+                "2026-10-17T14:30:00+02:00",
+                "Sat, 17 Oct 2026 14:30:00 +0200",
+                "1792240200",
+            ]
+        );
+        assert_eq!(answer("now")[0].detail, "Europe/Berlin (CEST, UTC+02:00)");
+    }
+
+    #[test]
+    fn other_zones() {
+        assert_eq!(
+            values("time in tokyo")[0],
+            "Saturday, 17 October 2026 21:30:00"
+        );
+        assert_eq!(
+            answer("time in tokyo")[0].detail,
+            "Asia/Tokyo (JST, UTC+09:00)"
+        );
+        assert_eq!(find_zone("new york"), Some(Tz::America__New_York));
+        assert_eq!(find_zone("utc"), Some(Tz::Etc__UTC));
+        assert_eq!(find_zone("atlantis"), None);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            values("unix 1700000000")[0],
+            "Tuesday, 14 November 2023 23:13:20"
+        );
+        assert_eq!(values("now + 3h")[3], "1792251000");
+    }
+
+    #[test]
+    fn date_arithmetic() {
+        assert_eq!(values("2026-10-17 + 45 days"), ["2026-12-01"]);
+        assert_eq!(values("today + 1 month"), ["2026-11-17"]);
+        // clamped to the end of a shorter month
+        assert_eq!(values("2026-01-31 + 1 mo"), ["2026-02-28"]);
+    }
+
+    #[test]
+    fn days_between() {
+        assert_eq!(values("days until dec 25"), ["69"]);
+        assert_eq!(values("days since 2020-01-01"), ["2481"]);
+    }
+
+    #[test]
+    fn weeks() {
+        assert_eq!(values("week number"), ["42"]);
+        assert_eq!(values("week of 2026-01-01"), ["1"]);
+    }
+
+    #[test]
+    fn other_queries_arent_answered() {
+        assert!(answer("42").is_empty());
+        assert!(answer("firefox").is_empty());
+        assert!(answer("5 km in mi").is_empty());
+    }
+
+    #[test]
+    fn years_rfc_2822_cant_hold() {
+        let far = answer("now + 10000 years");
+        assert_eq!(far[1].value, "+12026-10-17T14:30:00+01:00");
+        assert!(far.iter().all(|answer| answer.detail != "RFC 2822"));
+        let past = answer("unix -99999999999");
+        assert_eq!(past.last().unwrap().value, "-99999999999");
+        assert!(past.iter().all(|answer| answer.detail != "RFC 2822"));
+    }
+
+    #[test]
+    fn out_of_range_is_no_answer() {
+        assert!(answer("now + 99999999999 years").is_empty());
+        assert!(answer("unix 99999999999999999").is_empty());
+        assert!(answer("2026-10-17 + 9999999999 days").is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+use futures::{StreamExt, channel::mpsc};
+use global_hotkey::{GlobalHotKeyEvent, HotKeyState};
+use gpui::{App, AsyncApp, Entity};
+use tray_icon::menu::MenuEvent;
+
+use crate::{
+    action::{Launcher, SystemLauncher},
+    clipboard::ClipboardWatch,
+    config::Config,
+    hotkey::Hotkeys,
+    indexer::Indexer,
+    mode::Mode,
+    panel::Panel,
+    tray::{Tray, TrayCommand},
+};
+
+// everything from outside gpui that drives the panel
+pub enum Event {
+    Hotkey(GlobalHotKeyEvent),
+    Menu(MenuEvent),
+}
+
+// forwards events into the executor as they arrive, so nothing wakes up while idle
+pub fn start(panel: Entity<Panel>, cx: &mut App) {
+    let (tx, mut rx) = mpsc::unbounded();
+
+    // called on the thread that receives the os event, replaces the crates' own channels
+    GlobalHotKeyEvent::set_event_handler(Some({
+        let tx = tx.clone();
+        move |event: GlobalHotKeyEvent| {
+            let _ = tx.unbounded_send(Event::Hotkey(event));
+        }
+    }));
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        let _ = tx.unbounded_send(Event::Menu(event));
+    }));
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        // with `hold_to_show`, the hotkey holding the panel open
+        let mut held = None;
+        while let Some(event) = rx.next().await {
+            let _ = cx.update(|cx| dispatch(event, &panel, &mut held, cx));
+        }
+    })
+    .detach();
+}
+
+fn dispatch(event: Event, panel: &Entity<Panel>, held: &mut Option<u32>, cx: &mut App) {
+    match event {
+        Event::Hotkey(event) => hotkey(event, panel, held, cx),
+        Event::Menu(event) => {
+            if let Some(command) = TrayCommand::from_id(event.id()) {
+                menu(command, panel, cx);
+            }
+        }
+    }
+}
+
+fn hotkey(event: GlobalHotKeyEvent, panel: &Entity<Panel>, held: &mut Option<u32>, cx: &mut App) {
+    let Some(mode) = cx.global::<Hotkeys>().mode(event.id) else {
+        return;
+    };
+    let hold_to_show = cx.global::<Config>().panel.hold_to_show;
+
+    panel.update(cx, |panel, cx| {
+        let open_mode = panel.open_mode(cx);
+        match respond(event.state, event.id, mode, hold_to_show, held, open_mode) {
+            Response::Toggle(mode) => panel.toggle(mode, cx),
+            Response::Open(mode) => panel.open(mode, cx),
+            Response::Hide => panel.hide(cx),
+            Response::Ignore => {}
+        }
+    });
+}
+
+#[derive(Debug, PartialEq)]
+enum Response {
+    Toggle(Mode),
+    Open(Mode),
+    Hide,
+    Ignore,
+}
+
+// with `hold_to_show` a press opens the panel and releasing that same hotkey hides it, unless
+// the panel has since been switched to another mode or hidden
+fn respond(
+    state: HotKeyState,
+    id: u32,
+    mode: Mode,
+    hold_to_show: bool,
+    held: &mut Option<u32>,
+    open_mode: Option<Mode>,
+) -> Response {
+    match (state, hold_to_show) {
+        (HotKeyState::Pressed, false) => Response::Toggle(mode),
+        (HotKeyState::Pressed, true) => {
+            *held = Some(id);
+            Response::Open(mode)
+        }
+        (HotKeyState::Released, true) if *held == Some(id) => {
+            *held = None;
+            if open_mode == Some(mode) {
+                Response::Hide
+            } else {
+                Response::Ignore
+            }
+        }
+        (HotKeyState::Released, _) => Response::Ignore,
+    }
+}
+
+fn menu(command: TrayCommand, panel: &Entity<Panel>, cx: &mut App) {
+    match command {
+        TrayCommand::Show => panel.update(cx, |panel, cx| panel.open(Mode::Search, cx)),
+        TrayCommand::Preferences => {
+            if let Err(err) = SystemLauncher.edit(&Config::path()) {
+                report("failed to open preferences", &err.to_string());
+            }
+        }
+        TrayCommand::ReloadIndex => {
+            let indexer = cx.global::<Indexer>().clone();
+            cx.background_spawn(async move { indexer.rebuild() })
+                .detach();
+        }
+        TrayCommand::PauseHotkeys => {
+            let config = cx.global::<Config>().hotkeys.clone();
+            let hotkeys = cx.global_mut::<Hotkeys>();
+            if hotkeys.is_paused() {
+                if let Err(err) = hotkeys.resume(&config) {
+                    report("halcyon hotkey not registered", &err.to_string());
+                }
+            } else {
+                hotkeys.pause();
+            }
+            let paused = hotkeys.is_paused();
+            if let Some(tray) = cx.try_global::<Tray>() {
+                tray.set_hotkeys_paused(paused);
+            }
+        }
+        TrayCommand::PauseClipboard => {
+            let Some(watch) = cx.try_global::<ClipboardWatch>() else {
+                return;
+            };
+            let paused = !watch.is_paused();
+            watch.set_paused(paused);
+            if let Some(tray) = cx.try_global::<Tray>() {
+                tray.set_clipboard_paused(paused);
+            }
+        }
+        TrayCommand::Quit => cx.quit(),
+    }
+}
+
+fn report(title: &str, message: &str) {
+    eprintln!("{title}: {message}");
+    if let Err(err) = SystemLauncher.notify(title, message) {
+        eprintln!("failed to show notification: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH: u32 = 1;
+    const CLIPBOARD: u32 = 2;
+
+    fn press(id: u32, hold_to_show: bool, held: &mut Option<u32>, open: Option<Mode>) -> Response {
+        respond(HotKeyState::Pressed, id, mode(id), hold_to_show, held, open)
+    }
+
+    fn release(
+        id: u32,
+        hold_to_show: bool,
+        held: &mut Option<u32>,
+        open: Option<Mode>,
+    ) -> Response {
+        respond(
+            HotKeyState::Released,
+            id,
+            mode(id),
+            hold_to_show,
+            held,
+            open,
+        )
+    }
+
+    fn mode(id: u32) -> Mode {
+        if id == SEARCH {
+            Mode::Search
+        } else {
+            Mode::Clipboard
+        }
+    }
+
+    #[test]
+    fn presses_toggle() {
+        let mut held = None;
+        for open in [None, Some(Mode::Search)] {
+            assert_eq!(
+                press(SEARCH, false, &mut held, open),
+                Response::Toggle(Mode::Search)
+            );
+            assert_eq!(release(SEARCH, false, &mut held, open), Response::Ignore);
+        }
+        assert_eq!(held, None);
+    }
+
+    #[test]
+    fn hold_to_show() {
+        let mut held = None;
+        assert_eq!(
+            press(SEARCH, true, &mut held, None),
+            Response::Open(Mode::Search)
+        );
+        assert_eq!(held, Some(SEARCH));
+        assert_eq!(
+            release(SEARCH, true, &mut held, Some(Mode::Search)),
+            Response::Hide
+        );
+        assert_eq!(held, None);
+    }
+
+    #[test]
+    fn only_the_holding_hotkey_hides() {
+        let mut held = None;
+        press(SEARCH, true, &mut held, None);
+        // e.g. pressed before hold_to_show was turned on
+        assert_eq!(
+            release(CLIPBOARD, true, &mut held, Some(Mode::Search)),
+            Response::Ignore
+        );
+        assert_eq!(held, Some(SEARCH));
+
+        // another hotkey pressed while holding takes over
+        let open = Some(Mode::Search);
+        assert_eq!(
+            press(CLIPBOARD, true, &mut held, open),
+            Response::Open(Mode::Clipboard)
+        );
+        let open = Some(Mode::Clipboard);
+        assert_eq!(release(SEARCH, true, &mut held, open), Response::Ignore);
+        assert_eq!(release(CLIPBOARD, true, &mut held, open), Response::Hide);
+    }
+
+    #[test]
+    fn release_leaves_a_switched_or_hidden_panel() {
+        let mut held = None;
+        press(SEARCH, true, &mut held, None);
+        // switched from the tray while the hotkey was held
+        assert_eq!(
+            release(SEARCH, true, &mut held, Some(Mode::Files)),
+            Response::Ignore
+        );
+        assert_eq!(held, None);
+
+        press(SEARCH, true, &mut held, None);
+        assert_eq!(release(SEARCH, true, &mut held, None), Response::Ignore);
+        assert_eq!(held, None);
+    }
+}
@@ -0,0 +1,852 @@
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Days, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+
+use crate::{
+    config::expand_home,
+    fuzzy::{self, ScoredItem},
+    query::{Parser, Term},
+    search::{Search, SearchItem, SearchProvider, SearchStatus},
+};
+
+// any other `key:` is part of a name
+pub const KEYS: [&str; 4] = ["kind", "in", "modified", "content"];
+
+fn parser() -> Parser {
+    Parser::new().with_keys(KEYS)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Folder,
+    Application,
+    Pdf,
+    Image,
+    Audio,
+    Video,
+    Text,
+    Archive,
+    // anything else is taken as a file extension, e.g. `kind:rs`
+    Extension(String),
+}
+
+impl Kind {
+    pub fn parse(name: &str) -> Kind {
+        match name.trim_start_matches('.').to_lowercase().as_str() {
+            "folder" | "dir" | "directory" => Kind::Folder,
+            "app" | "application" => Kind::Application,
+            "pdf" => Kind::Pdf,
+            "image" | "img" | "picture" => Kind::Image,
+            "audio" | "music" => Kind::Audio,
+            "video" | "movie" => Kind::Video,
+            "text" => Kind::Text,
+            "archive" => Kind::Archive,
+            other => Kind::Extension(other.to_string()),
+        }
+    }
+
+    // the uniform type spotlight files it under
+    fn uti(&self) -> Option<&'static str> {
+        Some(match self {
+            Kind::Folder => "public.folder",
+            Kind::Application => "com.apple.application",
+            Kind::Pdf => "com.adobe.pdf",
+            Kind::Image => "public.image",
+            Kind::Audio => "public.audio",
+            Kind::Video => "public.movie",
+            Kind::Text => "public.text",
+            Kind::Archive => "public.archive",
+            Kind::Extension(_) => return None,
+        })
+    }
+
+    // what the built-in search goes by instead
+    fn extensions(&self) -> &[&str] {
+        match self {
+            Kind::Folder | Kind::Application | Kind::Extension(_) => &[],
+            Kind::Pdf => &["pdf"],
+            Kind::Image => &[
+                "png", "jpg", "jpeg", "gif", "heic", "heif", "tif", "tiff", "webp", "bmp", "svg",
+            ],
+            Kind::Audio => &[
+                "mp3", "m4a", "aac", "wav", "flac", "aif", "aiff", "ogg", "opus",
+            ],
+            Kind::Video => &["mp4", "m4v", "mov", "avi", "mkv", "webm"],
+            Kind::Text => &[
+                "txt", "md", "markdown", "rtf", "csv", "tsv", "json", "toml", "yaml", "yml", "xml",
+                "html", "css", "log",
+            ],
+            Kind::Archive => &[
+                "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "dmg",
+            ],
+        }
+    }
+
+    fn matches(&self, file: &FileInfo) -> bool {
+        let extension = file
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match self {
+            Kind::Folder => file.is_dir && extension.as_deref() != Some("app"),
+            Kind::Application => file.is_dir && extension.as_deref() == Some("app"),
+            Kind::Extension(wanted) => !file.is_dir && extension.as_deref() == Some(wanted),
+            kind => {
+                !file.is_dir
+                    && extension.is_some_and(|extension| kind.extensions().contains(&&*extension))
+            }
+        }
+    }
+}
+
+// one condition on a file, backends translate these into whatever they search with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    // the file name contains it, ignoring case
+    Name(String),
+    Content(String),
+    Kind(Kind),
+    // at or after `after`, and before `before`
+    Modified {
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    },
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileQuery {
+    // all of them have to hold
+    pub conditions: Vec<Condition>,
+    // folders to search in, everywhere if empty
+    pub scopes: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    EmptyValue(String),
+    BadModified(String),
+    NegatedScope,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::EmptyValue(key) => write!(f, "`{key}:` needs a value"),
+            QueryError::BadModified(value) => write!(
+                f,
+                "`modified:{value}` should be like `<7d`, `>2w`, `2026-01-31` or `today`"
+            ),
+            QueryError::NegatedScope => write!(f, "`in:` can't be negated"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl FileQuery {
+    // `now` resolves relative dates like `modified:<7d`
+    pub fn parse(query: &str, now: DateTime<FixedOffset>) -> Result<FileQuery, QueryError> {
+        let mut file_query = FileQuery::default();
+        for term in parser().parse(query).terms {
+            match term {
+                Term::Filter { key, value } if key.text == "in" => {
+                    if value.text.is_empty() {
+                        return Err(QueryError::EmptyValue(key.text));
+                    }
+                    file_query.scopes.push(expand_home(Path::new(&value.text)));
+                }
+                Term::Word(text) | Term::Phrase(text) if text.text.is_empty() => {}
+                term => file_query.conditions.push(condition(term, now)?),
+            }
+        }
+        Ok(file_query)
+    }
+
+    // there's nothing to search for without a condition, the scopes alone would list
+    // everything in them
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    // `content` reports whether the file contains a string, it's only asked if needed
+    pub fn matches(&self, file: &FileInfo, content: &mut dyn FnMut(&str) -> bool) -> bool {
+        let in_scope = self.scopes.is_empty()
+            || self
+                .scopes
+                .iter()
+                .any(|scope| file.path.starts_with(scope) && file.path != scope);
+        in_scope
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(file, content))
+    }
+}
+
+fn condition(term: Term, now: DateTime<FixedOffset>) -> Result<Condition, QueryError> {
+    Ok(match term {
+        Term::Word(text) | Term::Phrase(text) => Condition::Name(text.text),
+        Term::Filter { key, value } if value.text.is_empty() => {
+            return Err(QueryError::EmptyValue(key.text));
+        }
+        Term::Filter { key, value } => match key.text.as_str() {
+            // `kind:pdf,image` is either
+            "kind" => {
+                let mut kinds: Vec<Condition> = value
+                    .text
+                    .split(',')
+                    .filter(|kind| !kind.is_empty())
+                    .map(|kind| Condition::Kind(Kind::parse(kind)))
+                    .collect();
+                if kinds.len() == 1 {
+                    kinds.remove(0)
+                } else {
+                    Condition::Any(kinds)
+                }
+            }
+            "modified" => modified(&value.text, now)?,
+            "content" => Condition::Content(value.text),
+            // only `in:` is left, and it's only a condition when negated
+            _ => return Err(QueryError::NegatedScope),
+        },
+        Term::Not { term, .. } => Condition::Not(Box::new(condition(*term, now)?)),
+    })
+}
+
+fn modified(value: &str, now: DateTime<FixedOffset>) -> Result<Condition, QueryError> {
+    let bad = || QueryError::BadModified(value.to_string());
+    let (op, rest) = match value.chars().next() {
+        Some(op @ ('<' | '>')) => (Some(op), &value[1..]),
+        _ => (None, value),
+    };
+
+    // a day, from midnight to midnight in the query's time zone
+    let day = match rest.to_lowercase().as_str() {
+        "today" => Some(now.date_naive()),
+        "yesterday" => now.date_naive().pred_opt(),
+        rest => NaiveDate::parse_from_str(rest, "%Y-%m-%d").ok(),
+    };
+    if let Some(day) = day {
+        let midnight = |day: NaiveDate| {
+            now.timezone()
+                .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+                .earliest()
+                .map(|at| at.with_timezone(&Utc))
+        };
+        let start = midnight(day).ok_or_else(bad)?;
+        let end = midnight(day.checked_add_days(Days::new(1)).ok_or_else(bad)?).ok_or_else(bad)?;
+        return Ok(match op {
+            Some('<') => Condition::Modified {
+                after: None,
+                before: Some(start),
+            },
+            Some(_) => Condition::Modified {
+                after: Some(end),
+                before: None,
+            },
+            None => Condition::Modified {
+                after: Some(start),
+                before: Some(end),
+            },
+        });
+    }
+
+    // an age, `<7d` is newer than a week and `>7d` older
+    let split = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(bad)?;
+    let count: i64 = rest[..split].parse().map_err(|_| bad())?;
+    let hours = match &rest[split..] {
+        "h" => 1,
+        "d" => 24,
+        "w" => 24 * 7,
+        "y" => 24 * 365,
+        _ => return Err(bad()),
+    };
+    let age =
+        chrono::Duration::try_hours(count.checked_mul(hours).ok_or_else(bad)?).ok_or_else(bad)?;
+    // an age reaching before the earliest date there is
+    let cutoff = now
+        .with_timezone(&Utc)
+        .checked_sub_signed(age)
+        .ok_or_else(bad)?;
+    Ok(match op {
+        Some('>') => Condition::Modified {
+            after: None,
+            before: Some(cutoff),
+        },
+        _ => Condition::Modified {
+            after: Some(cutoff),
+            before: None,
+        },
+    })
+}
+
+// what the built-in search knows about a file without opening it
+pub struct FileInfo<'a> {
+    pub path: &'a Path,
+    pub name: &'a str,
+    pub is_dir: bool,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Condition {
+    pub fn matches(&self, file: &FileInfo, content: &mut dyn FnMut(&str) -> bool) -> bool {
+        match self {
+            Condition::Name(name) => file.name.to_lowercase().contains(&name.to_lowercase()),
+            Condition::Content(text) => !file.is_dir && content(text),
+            Condition::Kind(kind) => kind.matches(file),
+            Condition::Modified { after, before } => file.modified.is_some_and(|modified| {
+                after.is_none_or(|after| modified >= after)
+                    && before.is_none_or(|before| modified < before)
+            }),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.matches(file, content)),
+            Condition::Not(condition) => !condition.matches(file, content),
+        }
+    }
+
+    // NSPredicate format syntax, for an NSMetadataQuery
+    fn predicate(&self) -> String {
+        match self {
+            Condition::Name(name) => format!("kMDItemFSName CONTAINS[cd] {}", quote(name)),
+            Condition::Content(text) => format!("kMDItemTextContent CONTAINS[cd] {}", quote(text)),
+            Condition::Kind(Kind::Extension(extension)) => {
+                format!(
+                    "kMDItemFSName ENDSWITH[c] {}",
+                    quote(&format!(".{extension}"))
+                )
+            }
+            Condition::Kind(kind) => {
+                format!(
+                    "kMDItemContentTypeTree == {}",
+                    quote(kind.uti().unwrap_or_default())
+                )
+            }
+            Condition::Modified { after, before } => {
+                let after = after.map(|at| format!("kMDItemFSContentChangeDate >= {}", date(at)));
+                let before = before.map(|at| format!("kMDItemFSContentChangeDate < {}", date(at)));
+                match (after, before) {
+                    (Some(after), Some(before)) => format!("({after} AND {before})"),
+                    (Some(one), None) | (None, Some(one)) => one,
+                    (None, None) => "TRUEPREDICATE".to_string(),
+                }
+            }
+            Condition::Any(conditions) if conditions.is_empty() => "FALSEPREDICATE".to_string(),
+            Condition::Any(conditions) => {
+                let any: Vec<String> = conditions.iter().map(Condition::predicate).collect();
+                format!("({})", any.join(" OR "))
+            }
+            Condition::Not(condition) => format!("NOT ({})", condition.predicate()),
+        }
+    }
+}
+
+// the conditions for spotlight, the scopes are set on the query separately
+pub fn spotlight_predicate(query: &FileQuery) -> String {
+    if query.conditions.is_empty() {
+        return "TRUEPREDICATE".to_string();
+    }
+    let all: Vec<String> = query.conditions.iter().map(Condition::predicate).collect();
+    all.join(" AND ")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// predicates compare dates as seconds since the start of 2001
+fn date(at: DateTime<Utc>) -> String {
+    let reference = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    let seconds = (at - reference).num_milliseconds() as f64 / 1000.0;
+    format!("CAST({seconds:.3}, \"NSDate\")")
+}
+
+// the name conditions are what results can be ranked by, every one of them has to match the
+// name on its own so a result's words may come in any order
+pub fn rank(
+    query: &str,
+    items: Vec<SearchItem>,
+    boost: &dyn Fn(&SearchItem) -> i64,
+) -> Vec<ScoredItem> {
+    let terms: Vec<String> = parser()
+        .parse(query)
+        .terms
+        .into_iter()
+        .filter_map(|term| match term {
+            Term::Word(text) | Term::Phrase(text) if !text.text.is_empty() => Some(text.text),
+            _ => None,
+        })
+        .collect();
+
+    let mut scored: Vec<ScoredItem> = items
+        .into_iter()
+        .filter_map(|item| {
+            let mut score = boost(&item);
+            let mut positions = Vec::new();
+            for term in &terms {
+                let found = fuzzy::fuzzy_match(term, &item.name)?;
+                score += found.score;
+                positions.extend(found.positions);
+            }
+            positions.sort_unstable();
+            positions.dedup();
+            Some(ScoredItem {
+                item,
+                score,
+                positions,
+            })
+        })
+        .collect();
+    // stable, so results without name terms keep the providers' order
+    scored.sort_by_key(|scored| std::cmp::Reverse(scored.score));
+    scored
+}
+
+// walks the file system itself, for when spotlight is off or not indexing
+pub struct FileProvider {
+    roots: Vec<PathBuf>,
+    max_depth: usize,
+}
+
+impl FileProvider {
+    pub const MAX_RESULTS: usize = 500;
+    // time spent walking per poll, so cancelling a search never waits long
+    pub const POLL_BUDGET: Duration = Duration::from_millis(5);
+    // contents are only searched in files up to this size
+    pub const MAX_CONTENT_BYTES: u64 = 1 << 20;
+
+    pub fn new(roots: Vec<PathBuf>, max_depth: usize) -> Self {
+        Self { roots, max_depth }
+    }
+}
+
+impl SearchProvider for FileProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let query = match FileQuery::parse(query, Local::now().fixed_offset()) {
+            Ok(query) => query,
+            Err(err) => {
+                eprintln!("invalid file query: {err}");
+                FileQuery::default()
+            }
+        };
+        let roots = if query.scopes.is_empty() {
+            &self.roots
+        } else {
+            &query.scopes
+        };
+        let pending = if query.is_empty() {
+            VecDeque::new()
+        } else {
+            roots
+                .iter()
+                .map(|root| (root.clone(), self.max_depth))
+                .collect()
+        };
+        Box::new(FileWalk {
+            query,
+            pending,
+            found: 0,
+        })
+    }
+}
+
+const BUNDLES: [&str; 5] = ["app", "bundle", "framework", "photoslibrary", "xcodeproj"];
+
+struct FileWalk {
+    query: FileQuery,
+    // directories still to read, with how deep below them to go
+    pending: VecDeque<(PathBuf, usize)>,
+    found: usize,
+}
+
+impl Search for FileWalk {
+    fn poll(&mut self, items: &mut Vec<SearchItem>) -> SearchStatus {
+        let started = Instant::now();
+        while started.elapsed() < FileProvider::POLL_BUDGET {
+            let Some((dir, depth)) = self.pending.pop_front() else {
+                return SearchStatus::Done;
+            };
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') {
+                    continue;
+                }
+                // not following symlinks, they could lead outside the roots or in circles
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let path = entry.path();
+                let file = FileInfo {
+                    path: &path,
+                    name: &name,
+                    is_dir: metadata.is_dir(),
+                    modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                };
+                let mut content = |text: &str| contains(&path, metadata.len(), text);
+                if self.query.matches(&file, &mut content) {
+                    items.push(SearchItem::file(name.clone(), path.clone()));
+                    self.found += 1;
+                    if self.found >= FileProvider::MAX_RESULTS {
+                        self.pending.clear();
+                        return SearchStatus::Done;
+                    }
+                }
+                // bundles like apps are files as far as users are concerned
+                let bundle = path
+                    .extension()
+                    .is_some_and(|extension| BUNDLES.iter().any(|bundle| extension == *bundle));
+                if metadata.is_dir() && depth > 1 && !bundle {
+                    self.pending.push_back((path, depth - 1));
+                }
+            }
+        }
+        SearchStatus::Gathering
+    }
+
+    fn cancel(&mut self) {
+        self.pending.clear();
+    }
+}
+
+fn contains(path: &Path, len: u64, text: &str) -> bool {
+    if len > FileProvider::MAX_CONTENT_BYTES {
+        return false;
+    }
+    let mut bytes = Vec::new();
+    if fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .is_err()
+    {
+        return false;
+    }
+    // binary files rarely decode, which skips them
+    std::str::from_utf8(&bytes)
+        .is_ok_and(|content| content.to_lowercase().contains(&text.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, sync::mpsc};
+
+    use super::*;
+    use crate::search::{MemoryProvider, Route, SearchQuery, Searcher};
+
+    fn now() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 17, 12, 0, 0)
+            .unwrap()
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap())
+    }
+
+    fn conditions(query: &str) -> Vec<Condition> {
+        FileQuery::parse(query, now()).unwrap().conditions
+    }
+
+    fn file(path: &'static str, is_dir: bool) -> FileInfo<'static> {
+        let path = Path::new(path);
+        FileInfo {
+            path,
+            name: path.file_name().unwrap().to_str().unwrap(),
+            is_dir,
+            modified: utc(2026, 10, 16, 0),
+        }
+    }
+
+    #[test]
+    fn parses_conditions_and_scopes() {
+        let query = FileQuery::parse(
+            r#"report "q3 plan" kind:pdf,image -content:secret in:/tmp/x note:x"#,
+            now(),
+        )
+        .unwrap();
+        assert_eq!(query.scopes, [PathBuf::from("/tmp/x")]);
+        assert_eq!(
+            query.conditions,
+            [
+                Condition::Name("report".to_string()),
+                Condition::Name("q3 plan".to_string()),
+                Condition::Any(vec![
+                    Condition::Kind(Kind::Pdf),
+                    Condition::Kind(Kind::Image)
+                ]),
+                Condition::Not(Box::new(Condition::Content("secret".to_string()))),
+                // not a key, so part of the name
+                Condition::Name("note:x".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            conditions("kind:.RS"),
+            [Condition::Kind(Kind::Extension("rs".to_string()))]
+        );
+        assert_eq!(conditions("kind:dir,"), [Condition::Kind(Kind::Folder)]);
+        assert!(FileQuery::parse(r#"in:/x """#, now()).unwrap().is_empty());
+        assert_eq!(
+            FileQuery::parse("-in:/x", now()),
+            Err(QueryError::NegatedScope)
+        );
+        for key in ["kind", "in", "content", "modified"] {
+            assert_eq!(
+                FileQuery::parse(&format!("a {key}:"), now()),
+                Err(QueryError::EmptyValue(key.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn modified_days() {
+        // days start at midnight in the query's time zone, two hours ahead of utc
+        assert_eq!(
+            conditions("modified:2026-10-01"),
+            [Condition::Modified {
+                after: utc(2026, 9, 30, 22),
+                before: utc(2026, 10, 1, 22),
+            }]
+        );
+        assert_eq!(
+            conditions("modified:>TODAY"),
+            [Condition::Modified {
+                after: utc(2026, 10, 17, 22),
+                before: None,
+            }]
+        );
+        assert_eq!(
+            conditions("modified:<yesterday"),
+            [Condition::Modified {
+                after: None,
+                before: utc(2026, 10, 15, 22),
+            }]
+        );
+    }
+
+    #[test]
+    fn modified_ages() {
+        assert_eq!(
+            conditions("modified:<7d"),
+            [Condition::Modified {
+                after: utc(2026, 10, 10, 10),
+                before: None,
+            }]
+        );
+        assert_eq!(
+            conditions("modified:3h"),
+            [Condition::Modified {
+                after: utc(2026, 10, 17, 7),
+                before: None,
+            }]
+        );
+        assert_eq!(
+            conditions("modified:>2w"),
+            [Condition::Modified {
+                after: None,
+                before: utc(2026, 10, 3, 10),
+            }]
+        );
+        assert_eq!(
+            conditions("modified:>1y"),
+            [Condition::Modified {
+                after: None,
+                before: utc(2025, 10, 17, 10),
+            }]
+        );
+
+        for value in [
+            "soon",
+            "7x",
+            "7",
+            "d",
+            "<",
+            "-7d",
+            "2026-13-01",
+            "7d7d",
+            "99999999999999999999d",
+            "99999999999999d",
+            // representable as hours, but before any date chrono knows
+            "<270000y",
+            "<1000000y",
+        ] {
+            assert_eq!(
+                FileQuery::parse(&format!("modified:{value}"), now()),
+                Err(QueryError::BadModified(value.to_string())),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn conditions_match_files() {
+        let mut never = |_: &str| false;
+        assert!(
+            Condition::Name("REP".to_string()).matches(&file("/a/report.pdf", false), &mut never)
+        );
+        assert!(
+            !Condition::Name("plan".to_string())
+                .matches(&file("/plan/report.pdf", false), &mut never)
+        );
+
+        let kind = |name: &str, path: &'static str, is_dir: bool| {
+            Condition::Kind(Kind::parse(name)).matches(&file(path, is_dir), &mut |_| false)
+        };
+        assert!(kind("pdf", "/a/Report.PDF", false));
+        assert!(!kind("pdf", "/a/report.pdf", true));
+        assert!(kind("image", "/a/photo.HEIC", false));
+        assert!(!kind("image", "/a/photo", false));
+        assert!(kind("folder", "/a/src", true));
+        assert!(!kind("folder", "/a/Safari.app", true));
+        assert!(kind("app", "/a/Safari.app", true));
+        assert!(kind("rs", "/a/main.rs", false));
+        assert!(!kind("rs", "/a/main.rsx", false));
+
+        // only asked about files, and only when needed
+        let asked = Cell::new(0);
+        let mut content = |text: &str| {
+            asked.set(asked.get() + 1);
+            text == "secret"
+        };
+        let secret = Condition::Content("secret".to_string());
+        assert!(secret.matches(&file("/a/plan.txt", false), &mut content));
+        assert!(!secret.matches(&file("/a/plans", true), &mut content));
+        let either = Condition::Any(vec![Condition::Name("plan".to_string()), secret]);
+        assert!(either.matches(&file("/a/plan.txt", false), &mut content));
+        assert_eq!(asked.get(), 1);
+        assert!(!Condition::Any(Vec::new()).matches(&file("/a/plan.txt", false), &mut content));
+
+        let recent = Condition::Modified {
+            after: utc(2026, 10, 16, 0),
+            before: utc(2026, 10, 17, 0),
+        };
+        assert!(recent.matches(&file("/a/plan.txt", false), &mut never));
+        let older = FileInfo {
+            modified: utc(2026, 10, 15, 23),
+            ..file("/a/plan.txt", false)
+        };
+        assert!(!recent.matches(&older, &mut never));
+        assert!(Condition::Not(Box::new(recent.clone())).matches(&older, &mut never));
+        let unknown = FileInfo {
+            modified: None,
+            ..file("/a/plan.txt", false)
+        };
+        assert!(!recent.matches(&unknown, &mut never));
+    }
+
+    #[test]
+    fn queries_match_in_their_scopes() {
+        let query =
+            FileQuery::parse("rep kind:pdf -content:secret in:/docs modified:<7d", now()).unwrap();
+        let mut never = |_: &str| false;
+        assert!(query.matches(&file("/docs/Report.PDF", false), &mut never));
+        assert!(!query.matches(&file("/docs/Report.PDF", false), &mut |_| true));
+        assert!(!query.matches(&file("/other/report.pdf", false), &mut never));
+        assert!(!query.matches(&file("/docsets/report.pdf", false), &mut never));
+        assert!(!query.matches(&file("/docs/report.txt", false), &mut never));
+
+        // the scope itself isn't a result
+        let query = FileQuery::parse("docs in:/docs", now()).unwrap();
+        assert!(!query.matches(&file("/docs", true), &mut never));
+    }
+
+    #[test]
+    fn spotlight_predicates() {
+        assert_eq!(spotlight_predicate(&FileQuery::default()), "TRUEPREDICATE");
+        let query = FileQuery::parse(
+            r#""re\\p \"x\"" -kind:folder kind:rs,pdf modified:<1d in:/docs"#,
+            now(),
+        )
+        .unwrap();
+        assert_eq!(
+            spotlight_predicate(&query),
+            r#"kMDItemFSName CONTAINS[cd] "re\\p \"x\"" AND NOT (kMDItemContentTypeTree == "public.folder") AND (kMDItemFSName ENDSWITH[c] ".rs" OR kMDItemContentTypeTree == "com.adobe.pdf") AND kMDItemFSContentChangeDate >= CAST(813837600.000, "NSDate")"#
+        );
+        let query = FileQuery::parse("content:plan modified:2001-01-01", now()).unwrap();
+        assert_eq!(
+            spotlight_predicate(&query),
+            r#"kMDItemTextContent CONTAINS[cd] "plan" AND (kMDItemFSContentChangeDate >= CAST(-7200.000, "NSDate") AND kMDItemFSContentChangeDate < CAST(79200.000, "NSDate"))"#
+        );
+    }
+
+    #[test]
+    fn ranks_by_every_name_term() {
+        let items = vec![
+            SearchItem::file("notes report.md", "/a".into()),
+            SearchItem::file("report notes.md", "/b".into()),
+            SearchItem::file("x.md", "/c".into()),
+        ];
+        let ranked = rank("notes report kind:md", items.clone(), &|_| 0);
+        assert_eq!(ranked.len(), 2);
+        let ranked = rank("kind:md", items, &|item| (item.name == "x.md") as i64);
+        let names: Vec<&str> = ranked
+            .iter()
+            .map(|scored| scored.item.name.as_str())
+            .collect();
+        assert_eq!(names, ["x.md", "notes report.md", "report notes.md"]);
+    }
+
+    #[test]
+    fn walks_the_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub/deep")).unwrap();
+        fs::create_dir_all(root.join("Thing.app/Contents")).unwrap();
+        fs::write(root.join("sub/deep/plan.txt"), "the Secret plan").unwrap();
+        fs::write(root.join("sub/plan.pdf"), "x").unwrap();
+        fs::write(root.join("Thing.app/Contents/plan.txt"), "x").unwrap();
+        fs::write(root.join(".plan.txt"), "x").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let planner = MemoryProvider::new(vec![SearchItem::file("planner", "/p".into())]);
+        let route = Route::new(
+            vec!["'".to_string()],
+            vec![Box::new(FileProvider::new(vec![root.to_path_buf()], 8))],
+        )
+        .with_ranker(rank);
+        let searcher = Searcher::new(vec![Box::new(planner)])
+            .with_debounce(Duration::ZERO)
+            .with_route(route);
+        std::thread::spawn(move || searcher.run(rx));
+        let search = |query: &str| {
+            let (response_tx, response_rx) = oneshot::channel();
+            tx.send(SearchQuery {
+                query: query.to_string(),
+                generation: 1,
+                response_tx,
+            })
+            .unwrap();
+            let mut names: Vec<String> = response_rx
+                .recv()
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|result| result.item.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        // not in hidden files or bundles
+        assert_eq!(search("' plan"), ["plan.pdf", "plan.txt"]);
+        assert_eq!(search("'plan content:secret"), ["plan.txt"]);
+        assert_eq!(search("'plan kind:pdf"), ["plan.pdf"]);
+        assert_eq!(search("plan"), ["planner"]);
+        assert!(search("'").is_empty());
+        let deep = root.join("sub/deep");
+        assert_eq!(
+            search(&format!("'plan in:{}", deep.display())),
+            ["plan.txt"]
+        );
+    }
+}
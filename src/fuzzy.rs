@@ -0,0 +1,329 @@
+use crate::search::SearchItem;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_CAMEL: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 6;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTEND: i64 = 1;
+const PENALTY_TYPO: i64 = 24;
+// below this a single typo matches too much to be useful, "vsc" would match anything with
+// "vs", "vc" or "sc" in it
+const MIN_TYPO_LEN: usize = 4;
+// above anything a fuzzy match can score
+const SCORE_KEYWORD: i64 = 1 << 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    // byte offsets of the matched chars in the candidate
+    pub positions: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoredItem {
+    pub item: SearchItem,
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+// scores every item against the query, drops the ones that don't match and sorts best first,
+// with `boost` added to the score of every item that matched
+pub fn rank_by(
+    query: &str,
+    items: Vec<SearchItem>,
+    boost: &dyn Fn(&SearchItem) -> i64,
+) -> Vec<ScoredItem> {
+    let mut scored: Vec<ScoredItem> = items
+        .into_iter()
+        .filter_map(|item| {
+            let Match { score, positions } = match_item(query, &item)?;
+            Some(ScoredItem {
+                score: score + boost(&item),
+                item,
+                positions,
+            })
+        })
+        .collect();
+
+    scored.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.item.name.len().cmp(&b.item.name.len()))
+            .then_with(|| a.item.name.cmp(&b.item.name))
+    });
+    scored
+}
+
+// the item's exact keyword, or else its name
+pub fn match_item(query: &str, item: &SearchItem) -> Option<Match> {
+    match &item.keyword {
+        Some(keyword) if !keyword.is_empty() && keyword.eq_ignore_ascii_case(query.trim()) => {
+            Some(Match {
+                score: SCORE_KEYWORD,
+                positions: Vec::new(),
+            })
+        }
+        _ => fuzzy_match(query, &item.name),
+    }
+}
+
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<Match> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let candidate: Vec<(usize, char)> = candidate.char_indices().collect();
+    if is_subsequence(&query, &candidate)
+        && let Some(m) = best_alignment(&query, &candidate)
+    {
+        return Some(m);
+    }
+    if query.len() < MIN_TYPO_LEN {
+        return None;
+    }
+
+    // one typo: a stray or wrong char is a deletion from the query, a swapped pair a transposition
+    let deletions = (0..query.len()).map(|i| {
+        let mut variant = query.clone();
+        variant.remove(i);
+        variant
+    });
+    let transpositions = (0..query.len() - 1).map(|i| {
+        let mut variant = query.clone();
+        variant.swap(i, i + 1);
+        variant
+    });
+
+    deletions
+        .chain(transpositions)
+        // most candidates can't match at all, that's cheap to rule out before aligning
+        .filter(|variant| is_subsequence(variant, &candidate))
+        .filter_map(|variant| best_alignment(&variant, &candidate))
+        .max_by(|a, b| a.score.cmp(&b.score))
+        .map(|m| Match {
+            score: m.score - PENALTY_TYPO,
+            ..m
+        })
+}
+
+fn chars_eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+// whether every query char appears in the candidate in order, which any alignment needs
+fn is_subsequence(query: &[char], candidate: &[(usize, char)]) -> bool {
+    let mut chars = candidate.iter();
+    query.iter().all(|&q| chars.any(|&(_, c)| chars_eq(q, c)))
+}
+
+fn bonus(candidate: &[(usize, char)], idx: usize) -> i64 {
+    let cur = candidate[idx].1;
+    let Some(&(_, prev)) = idx.checked_sub(1).and_then(|i| candidate.get(i)) else {
+        return BONUS_BOUNDARY;
+    };
+
+    if !prev.is_alphanumeric() && cur.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && cur.is_uppercase())
+        || (prev.is_alphabetic() && cur.is_numeric())
+    {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+fn gap_penalty(gap: usize) -> i64 {
+    match gap {
+        0 => 0,
+        gap => PENALTY_GAP_START + PENALTY_GAP_EXTEND * (gap as i64 - 1),
+    }
+}
+
+// finds the highest scoring way to match every query char, in order, against the candidate
+fn best_alignment(query: &[char], candidate: &[(usize, char)]) -> Option<Match> {
+    let (n, m) = (query.len(), candidate.len());
+    if n > m {
+        return None;
+    }
+
+    // scores[i][j] is the best score with query[i] matched at candidate[j]
+    let mut scores = vec![vec![None::<i64>; m]; n];
+    let mut from = vec![vec![0usize; m]; n];
+
+    for j in 0..m {
+        if chars_eq(query[0], candidate[j].1) {
+            scores[0][j] = Some(SCORE_MATCH + bonus(candidate, j) - gap_penalty(j) / 2);
+        }
+    }
+
+    for i in 1..n {
+        for j in i..m {
+            if !chars_eq(query[i], candidate[j].1) {
+                continue;
+            }
+
+            let mut best: Option<(i64, usize)> = None;
+            for (k, prev) in scores[i - 1].iter().enumerate().take(j).skip(i - 1) {
+                let Some(prev) = *prev else {
+                    continue;
+                };
+                let score = if k + 1 == j {
+                    prev + BONUS_CONSECUTIVE
+                } else {
+                    prev - gap_penalty(j - k - 1)
+                };
+                if best.is_none_or(|(best, _)| score > best) {
+                    best = Some((score, k));
+                }
+            }
+
+            if let Some((score, k)) = best {
+                scores[i][j] = Some(score + SCORE_MATCH + bonus(candidate, j));
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, score) = scores[n - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))?;
+
+    let mut positions = vec![0; n];
+    for i in (0..n).rev() {
+        positions[i] = candidate[j].0;
+        j = from[i][j];
+    }
+
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, candidate: &str) -> Option<Vec<usize>> {
+        fuzzy_match(query, candidate).map(|m| m.positions)
+    }
+
+    fn score(query: &str, candidate: &str) -> Option<i64> {
+        fuzzy_match(query, candidate).map(|m| m.score)
+    }
+
+    fn item(name: &str) -> SearchItem {
+        SearchItem::file(name, format!("/Applications/{name}.app").into())
+    }
+
+    #[test]
+    fn subsequences() {
+        // a match at the start, then one right after it
+        assert_eq!(score("s", "Safari"), Some(SCORE_MATCH + BONUS_BOUNDARY));
+        assert_eq!(
+            score("sa", "Safari"),
+            Some(2 * SCORE_MATCH + BONUS_BOUNDARY + BONUS_CONSECUTIVE)
+        );
+        assert_eq!(
+            score("sf", "Safari"),
+            Some(2 * SCORE_MATCH + BONUS_BOUNDARY - PENALTY_GAP_START)
+        );
+        assert_eq!(positions("SFR", "Safari"), Some(vec![0, 2, 4]));
+        assert_eq!(positions("", "Safari"), Some(Vec::new()));
+        assert_eq!(positions("s a", "Safari"), Some(vec![0, 1]));
+        assert_eq!(positions("ifs", "Safari"), None);
+    }
+
+    #[test]
+    fn word_starts_and_camel_case_score_higher() {
+        // `s` at a word start beats one inside a word
+        assert_eq!(positions("vs", "Visual Studio"), Some(vec![0, 7]));
+        assert!(score("ms", "Mac Safari") > score("ms", "Mosaic"));
+        assert_eq!(
+            score("ms", "MacSafari"),
+            Some(2 * SCORE_MATCH + BONUS_BOUNDARY + BONUS_CAMEL - gap_penalty(2))
+        );
+        assert_eq!(
+            score("ms", "Mosaic"),
+            Some(2 * SCORE_MATCH + BONUS_BOUNDARY - gap_penalty(1))
+        );
+        // and a letter after a digit's word
+        assert_eq!(positions("p3", "mp3 Player"), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn acronyms() {
+        assert_eq!(positions("vsc", "Visual Studio Code"), Some(vec![0, 7, 14]));
+        assert_eq!(score("vsc", "Visual Studio Code"), Some(62));
+        assert!(score("vsc", "Visual Studio Code") > score("vsc", "Vimscript"));
+        assert!(score("vsc", "VS Code") > score("vsc", "Visual Studio Code"));
+    }
+
+    #[test]
+    fn one_typo_in_longer_queries() {
+        // a swapped pair, a missing char and a stray one
+        assert_eq!(score("clac", "Calculator"), Some(92 - PENALTY_TYPO));
+        assert_eq!(positions("safri", "Safari"), Some(vec![0, 1, 2, 4, 5]));
+        assert_eq!(positions("xsafari", "Safari"), Some(vec![0, 1, 2, 3, 4, 5]));
+        assert!(score("safri", "Safari") < score("safari", "Safari"));
+        // not two
+        assert_eq!(score("sfxrx", "Safari"), None);
+        assert_eq!(score("xyzw", "Safari"), None);
+        // short queries have to match as typed, or "vsc" would match anything with "vs"
+        assert_eq!(score("vsc", "Canvas"), None);
+        assert_eq!(score("sfs", "Safari"), None);
+    }
+
+    #[test]
+    fn positions_are_byte_offsets() {
+        assert_eq!(positions("é", "Café"), Some(vec![3]));
+        assert_eq!(positions("a", "Éa"), Some(vec![2]));
+        assert_eq!(positions("日本", "日本語"), Some(vec![0, 3]));
+        assert_eq!(positions("🎉p", "🎉 Party"), Some(vec![0, 5]));
+        // case folding that changes the length
+        assert_eq!(positions("ß", "STRAßE"), Some(vec![4]));
+        assert_eq!(positions("ÉCOLE", "école"), Some(vec![0, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn keywords_beat_any_match() {
+        let mut safari = item("Safari");
+        safari.keyword = Some("web".to_string());
+        assert_eq!(match_item(" WEB ", &safari).unwrap().score, SCORE_KEYWORD);
+        assert_eq!(match_item("saf", &safari), fuzzy_match("saf", "Safari"));
+        assert_eq!(match_item("we", &safari), None);
+    }
+
+    #[test]
+    fn ranking() {
+        let items = ["iTerm", "Termb", "Terminal", "Photo Booth", "Terma", "Term"].map(item);
+        let ranked = rank_by("term", items.to_vec(), &|_| 0);
+        let names: Vec<(&str, i64)> = ranked
+            .iter()
+            .map(|scored| (scored.item.name.as_str(), scored.score))
+            .collect();
+        // equal scores go shortest first, then by name
+        assert_eq!(
+            names,
+            [
+                ("Term", 92),
+                ("Terma", 92),
+                ("Termb", 92),
+                ("Terminal", 92),
+                ("iTerm", 89)
+            ]
+        );
+
+        let ranked = rank_by("term", items.to_vec(), &|item| {
+            if item.name == "iTerm" { 10 } else { 0 }
+        });
+        assert_eq!(ranked[0].item.name, "iTerm");
+        assert_eq!(ranked[0].score, 99);
+        assert_eq!(ranked[0].positions, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::ops::Range;
+
+use gpui::TextRun;
+use unicode_segmentation::UnicodeSegmentation;
+
+// splits `text` into runs of `run`, with each highlighted byte range in its own style. ranges
+// are widened to whole graphemes so a match can't split one, touching ranges in the same style
+// are joined and ranges overlapping one in another style or out of bounds are skipped
+pub fn highlight_runs(
+    text: &str,
+    highlights: &[(Range<usize>, TextRun)],
+    run: &TextRun,
+) -> Vec<TextRun> {
+    let mut highlights: Vec<(Range<usize>, &TextRun)> = highlights
+        .iter()
+        .filter(|(range, _)| !range.is_empty() && range.end <= text.len())
+        .map(|(range, style)| (graphemes(text, range), style))
+        .collect();
+    highlights.sort_by_key(|(range, _)| range.start);
+
+    let mut runs: Vec<TextRun> = Vec::new();
+    let mut pos = 0;
+    let mut last: Option<&TextRun> = None;
+    for (mut range, style) in highlights {
+        if range.start < pos {
+            if !last.is_some_and(|last| same_style(last, style)) || range.end <= pos {
+                continue;
+            }
+            range.start = pos;
+        }
+        push(&mut runs, range.start - pos, run);
+        push(&mut runs, range.len(), style);
+        pos = range.end;
+        last = Some(style);
+    }
+    push(&mut runs, text.len() - pos, run);
+    runs
+}
+
+// the fuzzy matcher's positions, the byte offsets of matched chars, as a range each
+pub fn position_ranges(text: &str, positions: &[usize]) -> Vec<Range<usize>> {
+    positions
+        .iter()
+        .filter_map(|&pos| Some(pos..pos + text.get(pos..)?.chars().next()?.len_utf8()))
+        .collect()
+}
+
+// the smallest run of whole graphemes covering `range`
+fn graphemes(text: &str, range: &Range<usize>) -> Range<usize> {
+    let mut start = 0;
+    let mut end = text.len();
+    for (idx, _) in text.grapheme_indices(true) {
+        if idx <= range.start {
+            start = idx;
+        }
+        if idx >= range.end {
+            end = idx;
+            break;
+        }
+    }
+    start..end
+}
+
+// runs in the same style are joined, as if they'd been one
+fn push(runs: &mut Vec<TextRun>, len: usize, style: &TextRun) {
+    if len == 0 {
+        return;
+    }
+    if let Some(last) = runs.last_mut()
+        && same_style(last, style)
+    {
+        last.len += len;
+        return;
+    }
+    runs.push(TextRun {
+        len,
+        ..style.clone()
+    });
+}
+
+fn same_style(a: &TextRun, b: &TextRun) -> bool {
+    TextRun {
+        len: 0,
+        ..a.clone()
+    } == TextRun {
+        len: 0,
+        ..b.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::{font, hsla};
+
+    use super::*;
+
+    fn plain() -> TextRun {
+        TextRun {
+            len: 0,
+            font: font("Helvetica"),
+            color: hsla(0.0, 0.0, 0.9, 1.0),
+            background_color: None,
+            underline: None,
+            strikethrough: None,
+        }
+    }
+
+    fn blue() -> TextRun {
+        TextRun {
+            color: hsla(0.58, 0.9, 0.7, 1.0),
+            ..plain()
+        }
+    }
+
+    fn red() -> TextRun {
+        TextRun {
+            color: hsla(0.0, 0.9, 0.5, 1.0),
+            ..plain()
+        }
+    }
+
+    // each run as its text and whether it's the plain style
+    fn split(text: &str, highlights: &[(Range<usize>, TextRun)]) -> Vec<(String, bool)> {
+        let runs = highlight_runs(text, highlights, &plain());
+        assert_eq!(runs.iter().map(|run| run.len).sum::<usize>(), text.len());
+        let mut pos = 0;
+        runs.into_iter()
+            .map(|run| {
+                let part = text[pos..pos + run.len].to_string();
+                pos += run.len;
+                (part, same_style(&run, &plain()))
+            })
+            .collect()
+    }
+
+    fn matched(text: &str, positions: &[usize]) -> Vec<(String, bool)> {
+        let highlights: Vec<_> = position_ranges(text, positions)
+            .into_iter()
+            .map(|range| (range, blue()))
+            .collect();
+        split(text, &highlights)
+    }
+
+    fn parts(parts: &[(&str, bool)]) -> Vec<(String, bool)> {
+        parts
+            .iter()
+            .map(|(part, plain)| (part.to_string(), *plain))
+            .collect()
+    }
+
+    #[test]
+    fn consecutive_matches_are_one_run() {
+        assert_eq!(
+            matched("Safari", &[0, 1, 4]),
+            parts(&[("Sa", false), ("fa", true), ("r", false), ("i", true)])
+        );
+        assert_eq!(matched("Safari", &[]), parts(&[("Safari", true)]));
+    }
+
+    #[test]
+    fn accents() {
+        // precomposed, é is one char
+        assert_eq!(
+            matched("caf\u{e9}s", &[3]),
+            parts(&[("caf", true), ("\u{e9}", false), ("s", true)])
+        );
+        // combining, matching the e takes its accent with it
+        assert_eq!(
+            matched("cafe\u{301}s", &[3]),
+            parts(&[("caf", true), ("e\u{301}", false), ("s", true)])
+        );
+        // and matching only the accent takes its e
+        assert_eq!(
+            matched("cafe\u{301}s", &[4]),
+            parts(&[("caf", true), ("e\u{301}", false), ("s", true)])
+        );
+    }
+
+    #[test]
+    fn emoji_sequences_stay_whole() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let text = format!("a{family}b");
+        // the woman in the middle of the sequence
+        assert_eq!(
+            matched(&text, &[1 + 4 + 3]),
+            parts(&[("a", true), (family, false), ("b", true)])
+        );
+        assert_eq!(
+            matched(&text, &[0, 1 + 4 + 3, 1 + family.len()]),
+            parts(&[(&text, false)])
+        );
+    }
+
+    #[test]
+    fn cjk() {
+        // three bytes per char
+        assert_eq!(
+            matched("東京タワー", &[3, 6]),
+            parts(&[("東", true), ("京タ", false), ("ワー", true)])
+        );
+    }
+
+    #[test]
+    fn ranges_in_other_styles() {
+        let highlights = [(0..1, red()), (2..5, blue())];
+        assert_eq!(
+            split(">ls -la", &highlights),
+            parts(&[(">", false), ("l", true), ("s -", false), ("la", true)])
+        );
+        let runs = highlight_runs(">ls -la", &highlights, &plain());
+        assert_eq!(runs[0].color, red().color);
+        assert_eq!(runs[2].color, blue().color);
+
+        // overlapping another style, out of bounds or empty
+        let highlights = [
+            (0..3, red()),
+            (2..5, blue()),
+            (6..9, blue()),
+            (1..1, blue()),
+        ];
+        assert_eq!(
+            split(">ls -la", &highlights),
+            parts(&[(">ls", false), (" -la", true)])
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use gpui::Global;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Launch {
+    pub path: PathBuf,
+    pub query: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    launches: Vec<Launch>,
+}
+
+// every launched item, persisted so frecently used items can be ranked first
+#[derive(Clone)]
+pub struct History {
+    path: Arc<PathBuf>,
+    storage: Storage,
+    // off when the file was there but couldn't be read, so it isn't overwritten
+    writable: bool,
+    launches: Arc<Mutex<Vec<Launch>>>,
+}
+
+impl Global for History {}
+
+impl History {
+    pub const MAX_LAUNCHES: usize = 2000;
+    pub const MAX_AGE_DAYS: i64 = 90;
+    pub const HALF_LIFE_DAYS: f64 = 14.0;
+    // launches from a query sharing a prefix with the current one count this much more
+    pub const PREFIX_WEIGHT: f64 = 3.0;
+    pub const MAX_BOOST: i64 = 80;
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("halcyon")
+            .join("history.json")
+    }
+
+    // never fails, a missing or unreadable file just means an empty history
+    pub fn load(path: PathBuf, storage: Storage) -> Self {
+        let loaded = storage.load::<HistoryFile>(&path, "launch history", SCHEMA_VERSION);
+        let writable = loaded.writable;
+        let mut launches = loaded.file.map(|file| file.launches).unwrap_or_default();
+        Self::prune(&mut launches, Utc::now());
+
+        Self {
+            path: Arc::new(path),
+            storage,
+            writable,
+            launches: Arc::new(Mutex::new(launches)),
+        }
+    }
+
+    pub fn launches(&self) -> Vec<Launch> {
+        self.launches.lock().unwrap().clone()
+    }
+
+    pub fn record(&self, path: &Path, query: &str, now: DateTime<Utc>) -> io::Result<()> {
+        {
+            let mut launches = self.launches.lock().unwrap();
+            launches.push(Launch {
+                path: path.to_path_buf(),
+                query: query.to_string(),
+                at: now,
+            });
+            Self::prune(&mut launches, now);
+        }
+        self.save()
+    }
+
+    fn prune(launches: &mut Vec<Launch>, now: DateTime<Utc>) {
+        let cutoff = now - Duration::days(Self::MAX_AGE_DAYS);
+        launches.retain(|launch| launch.at >= cutoff);
+        if launches.len() > Self::MAX_LAUNCHES {
+            let excess = launches.len() - Self::MAX_LAUNCHES;
+            launches.drain(..excess);
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if !self.writable {
+            return Ok(());
+        }
+        let file = HistoryFile {
+            version: SCHEMA_VERSION,
+            launches: self.launches(),
+        };
+        let bytes = serde_json::to_vec(&file)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(self.storage.write(&self.path, &bytes)?)
+    }
+
+    // exponentially decayed launch count, weighted towards launches from similar queries
+    pub fn frecency(&self, path: &Path, query: &str, now: DateTime<Utc>) -> f64 {
+        let query = query.to_lowercase();
+        self.launches
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|launch| launch.path == path)
+            .map(|launch| {
+                let age_days = (now - launch.at).num_seconds().max(0) as f64 / 86_400.0;
+                let decay = 0.5f64.powf(age_days / Self::HALF_LIFE_DAYS);
+                let launch_query = launch.query.to_lowercase();
+                let shares_prefix = !query.is_empty()
+                    && !launch_query.is_empty()
+                    && (launch_query.starts_with(&query) || query.starts_with(&launch_query));
+                if shares_prefix {
+                    decay * Self::PREFIX_WEIGHT
+                } else {
+                    decay
+                }
+            })
+            .sum()
+    }
+
+    // frecency on the same scale as fuzzy match scores
+    pub fn boost(&self, path: &Path, query: &str, now: DateTime<Utc>) -> i64 {
+        ((self.frecency(path, query, now) * 8.0).round() as i64).min(Self::MAX_BOOST)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Secret;
+
+    const SAFARI: &str = "/Applications/Safari.app";
+    const NOTES: &str = "/Applications/Notes.app";
+
+    fn load(dir: &tempfile::TempDir) -> History {
+        History::load(dir.path().join("history.json"), Storage::plain())
+    }
+
+    fn days(days: f64) -> Duration {
+        Duration::seconds((days * 86_400.0) as i64)
+    }
+
+    #[test]
+    fn records_and_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let history = load(&dir);
+        history.record(Path::new(SAFARI), "saf", now).unwrap();
+        history.record(Path::new(NOTES), "no", now).unwrap();
+
+        let reloaded = load(&dir);
+        assert_eq!(reloaded.launches(), history.launches());
+        assert_eq!(reloaded.launches()[1].path, Path::new(NOTES));
+        assert_eq!(reloaded.launches()[1].query, "no");
+    }
+
+    #[test]
+    fn old_and_excess_launches_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let history = load(&dir);
+        let old = now - days(History::MAX_AGE_DAYS as f64 + 1.0);
+        history.record(Path::new(NOTES), "", old).unwrap();
+        history.record(Path::new(SAFARI), "", now).unwrap();
+        assert_eq!(history.launches().len(), 1);
+        assert_eq!(history.launches()[0].path, Path::new(SAFARI));
+
+        let mut launches = vec![
+            Launch {
+                path: SAFARI.into(),
+                query: String::new(),
+                at: now,
+            };
+            History::MAX_LAUNCHES
+        ];
+        launches[0].path = NOTES.into();
+        History::prune(&mut launches, now);
+        assert_eq!(launches.len(), History::MAX_LAUNCHES);
+        launches.push(launches[1].clone());
+        History::prune(&mut launches, now);
+        // the oldest go first
+        assert_eq!(launches.len(), History::MAX_LAUNCHES);
+        assert!(
+            launches
+                .iter()
+                .all(|launch| launch.path == Path::new(SAFARI))
+        );
+    }
+
+    #[test]
+    fn frecency_decays_and_favours_similar_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let history = load(&dir);
+        history.record(Path::new(SAFARI), "", now).unwrap();
+        history
+            .record(Path::new(NOTES), "", now - days(History::HALF_LIFE_DAYS))
+            .unwrap();
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-3;
+        assert!(close(history.frecency(Path::new(SAFARI), "", now), 1.0));
+        assert!(close(history.frecency(Path::new(NOTES), "", now), 0.5));
+        assert_eq!(
+            history.frecency(Path::new("/Applications/Mail.app"), "", now),
+            0.0
+        );
+
+        history.record(Path::new(SAFARI), "Saf", now).unwrap();
+        // a prefix either way counts, case aside
+        for query in ["s", "SAFARI", "saf"] {
+            assert!(close(
+                history.frecency(Path::new(SAFARI), query, now),
+                1.0 + History::PREFIX_WEIGHT
+            ));
+        }
+        assert!(close(
+            history.frecency(Path::new(SAFARI), "notes", now),
+            2.0
+        ));
+    }
+
+    #[test]
+    fn boost_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        let history = load(&dir);
+        history.record(Path::new(SAFARI), "", now).unwrap();
+        assert_eq!(history.boost(Path::new(SAFARI), "", now), 8);
+        for _ in 0..50 {
+            history.record(Path::new(SAFARI), "saf", now).unwrap();
+        }
+        assert_eq!(
+            history.boost(Path::new(SAFARI), "saf", now),
+            History::MAX_BOOST
+        );
+    }
+
+    #[test]
+    fn unusable_files_are_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        for contents in ["not json", r#"{"version": 1, "launches": 3}"#] {
+            fs::write(&path, contents).unwrap();
+            assert!(load(&dir).launches().is_empty());
+            assert_eq!(
+                fs::read_to_string(path.with_extension("json.corrupt")).unwrap(),
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn newer_files_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let contents = r#"{"version": 2, "launches": [], "visits": []}"#;
+        fs::write(&path, contents).unwrap();
+
+        let history = load(&dir);
+        assert!(history.launches().is_empty());
+        history.record(Path::new(SAFARI), "", Utc::now()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+        assert!(!path.with_extension("json.corrupt").exists());
+    }
+
+    #[test]
+    fn missing_key_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let storage = Storage::encrypted(Secret::Key([1; 32]));
+        let history = History::load(path.clone(), storage.clone());
+        history
+            .record(Path::new("/Applications/Safari.app"), "saf", Utc::now())
+            .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        // can't decrypt anything without a key
+        let history = History::load(path.clone(), Storage::without_key());
+        assert!(history.launches().is_empty());
+        history
+            .record(Path::new("/Applications/Notes.app"), "no", Utc::now())
+            .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+        assert!(!path.with_extension("json.locked").exists());
+        assert_eq!(History::load(path, storage).launches().len(), 1);
+    }
+
+    #[test]
+    fn damaged_files_are_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let history = History::load(path.clone(), Storage::encrypted(Secret::Key([1; 32])));
+        history
+            .record(Path::new("/Applications/Safari.app"), "saf", Utc::now())
+            .unwrap();
+
+        let history = History::load(path.clone(), Storage::encrypted(Secret::Key([2; 32])));
+        assert!(history.launches().is_empty());
+        assert!(path.with_extension("json.locked").exists());
+        history
+            .record(Path::new("/Applications/Notes.app"), "no", Utc::now())
+            .unwrap();
+        assert!(path.exists());
+    }
+}
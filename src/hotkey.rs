@@ -0,0 +1,296 @@
+use std::{collections::HashMap, fmt};
+
+use global_hotkey::{GlobalHotKeyManager, hotkey::HotKey};
+use gpui::Global;
+
+use crate::{
+    config::{HotkeyBinding, HotkeysConfig},
+    mode::Mode,
+};
+
+#[derive(Debug)]
+pub struct HotkeyError {
+    pub chord: String,
+    pub source: global_hotkey::Error,
+}
+
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            // the os refuses chords another application has registered
+            global_hotkey::Error::FailedToRegister(message) => write!(
+                f,
+                "hotkey \"{}\" couldn't be registered, it may be taken by another application \
+                 ({message})",
+                self.chord
+            ),
+            global_hotkey::Error::AlreadyRegistered(_) => write!(
+                f,
+                "hotkey \"{}\" is already registered by halcyon",
+                self.chord
+            ),
+            source => write!(f, "failed to register hotkey \"{}\": {source}", self.chord),
+        }
+    }
+}
+
+impl std::error::Error for HotkeyError {}
+
+// registers hotkeys with the os, so it can be swapped out
+pub trait Registrar {
+    fn register(&self, hotkey: HotKey) -> global_hotkey::Result<()>;
+    fn unregister_all(&self, hotkeys: &[HotKey]) -> global_hotkey::Result<()>;
+}
+
+impl Registrar for GlobalHotKeyManager {
+    fn register(&self, hotkey: HotKey) -> global_hotkey::Result<()> {
+        GlobalHotKeyManager::register(self, hotkey)
+    }
+
+    fn unregister_all(&self, hotkeys: &[HotKey]) -> global_hotkey::Result<()> {
+        GlobalHotKeyManager::unregister_all(self, hotkeys)
+    }
+}
+
+// owns the registrations with the os, so hotkeys can be swapped when the config changes
+pub struct Hotkeys {
+    registrar: Box<dyn Registrar>,
+    registered: Vec<HotkeyBinding>,
+    modes: HashMap<u32, Mode>,
+    paused: bool,
+}
+
+impl Global for Hotkeys {}
+
+impl Hotkeys {
+    pub fn new() -> global_hotkey::Result<Self> {
+        Ok(Self::with_registrar(GlobalHotKeyManager::new()?))
+    }
+
+    pub fn with_registrar(registrar: impl Registrar + 'static) -> Self {
+        Self {
+            registrar: Box::new(registrar),
+            registered: Vec::new(),
+            modes: HashMap::new(),
+            paused: false,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // releases every hotkey, so other applications can use the chords for a while
+    pub fn pause(&mut self) {
+        let registered = std::mem::take(&mut self.registered);
+        self.unregister(&registered);
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self, config: &HotkeysConfig) -> Result<(), HotkeyError> {
+        self.paused = false;
+        self.apply(config)
+    }
+
+    // the mode bound to a `GlobalHotKeyEvent::id`
+    pub fn mode(&self, id: u32) -> Option<Mode> {
+        self.modes.get(&id).copied()
+    }
+
+    // all or nothing, on failure the previously registered hotkeys stay active
+    pub fn apply(&mut self, config: &HotkeysConfig) -> Result<(), HotkeyError> {
+        // registered, and checked for conflicts, on resume
+        if self.paused {
+            return Ok(());
+        }
+        if self.registered == config.bindings {
+            return Ok(());
+        }
+
+        let old = std::mem::take(&mut self.registered);
+        self.unregister(&old);
+
+        if let Err(err) = self.register(&config.bindings) {
+            if let Err(err) = self.register(&old) {
+                eprintln!("failed to restore previous hotkeys: {err}");
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn register(&mut self, bindings: &[HotkeyBinding]) -> Result<(), HotkeyError> {
+        for (idx, binding) in bindings.iter().enumerate() {
+            if let Err(source) = self.registrar.register(binding.hotkey) {
+                self.unregister(&bindings[..idx]);
+                return Err(HotkeyError {
+                    chord: binding.chord.clone(),
+                    source,
+                });
+            }
+        }
+
+        self.registered = bindings.to_vec();
+        self.modes = bindings
+            .iter()
+            .map(|binding| (binding.hotkey.id(), binding.mode))
+            .collect();
+        Ok(())
+    }
+
+    fn unregister(&mut self, bindings: &[HotkeyBinding]) {
+        let hotkeys: Vec<HotKey> = bindings.iter().map(|binding| binding.hotkey).collect();
+        if let Err(err) = self.registrar.unregister_all(&hotkeys) {
+            eprintln!("failed to unregister hotkeys: {err}");
+        }
+        self.registered.clear();
+        self.modes.clear();
+    }
+}
+
+// like the os, refuses `taken` chords and ones it already has
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct FakeRegistrar {
+    pub taken: Vec<HotKey>,
+    pub registered: std::rc::Rc<std::cell::RefCell<Vec<HotKey>>>,
+}
+
+#[cfg(test)]
+impl Registrar for FakeRegistrar {
+    fn register(&self, hotkey: HotKey) -> global_hotkey::Result<()> {
+        let mut registered = self.registered.borrow_mut();
+        if self.taken.contains(&hotkey) {
+            return Err(global_hotkey::Error::FailedToRegister(
+                "RegisterEventHotKey failed".to_string(),
+            ));
+        }
+        if registered.contains(&hotkey) {
+            return Err(global_hotkey::Error::AlreadyRegistered(hotkey));
+        }
+        registered.push(hotkey);
+        Ok(())
+    }
+
+    fn unregister_all(&self, hotkeys: &[HotKey]) -> global_hotkey::Result<()> {
+        self.registered
+            .borrow_mut()
+            .retain(|hotkey| !hotkeys.contains(hotkey));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn config(chords: &[(&str, Mode)]) -> HotkeysConfig {
+        let table: BTreeMap<String, Mode> = chords
+            .iter()
+            .map(|(chord, mode)| (chord.to_string(), *mode))
+            .collect();
+        HotkeysConfig::from(table)
+    }
+
+    fn hotkey(chord: &str) -> HotKey {
+        chord.parse().unwrap()
+    }
+
+    #[test]
+    fn apply_registers_and_maps_modes() {
+        let registrar = FakeRegistrar::default();
+        let mut hotkeys = Hotkeys::with_registrar(registrar.clone());
+        hotkeys
+            .apply(&config(&[
+                ("alt+space", Mode::Search),
+                ("cmd+shift+v", Mode::Clipboard),
+            ]))
+            .unwrap();
+        assert_eq!(registrar.registered.borrow().len(), 2);
+        assert_eq!(hotkeys.mode(hotkey("alt+space").id()), Some(Mode::Search));
+        assert_eq!(
+            hotkeys.mode(hotkey("cmd+shift+v").id()),
+            Some(Mode::Clipboard)
+        );
+
+        hotkeys
+            .apply(&config(&[("ctrl+space", Mode::Files)]))
+            .unwrap();
+        assert_eq!(*registrar.registered.borrow(), [hotkey("ctrl+space")]);
+        assert_eq!(hotkeys.mode(hotkey("alt+space").id()), None);
+        assert_eq!(hotkeys.mode(hotkey("ctrl+space").id()), Some(Mode::Files));
+    }
+
+    #[test]
+    fn partial_failure_keeps_the_previous_hotkeys() {
+        let registrar = FakeRegistrar {
+            taken: vec![hotkey("ctrl+space")],
+            ..Default::default()
+        };
+        let mut hotkeys = Hotkeys::with_registrar(registrar.clone());
+        hotkeys
+            .apply(&config(&[("alt+space", Mode::Search)]))
+            .unwrap();
+
+        // cmd+shift+v registers before ctrl+space fails, and is rolled back
+        let err = hotkeys
+            .apply(&config(&[
+                ("cmd+shift+v", Mode::Clipboard),
+                ("ctrl+space", Mode::Files),
+            ]))
+            .unwrap_err();
+        assert_eq!(err.chord, "ctrl+space");
+        assert!(err.to_string().contains("another application"), "{err}");
+        assert_eq!(*registrar.registered.borrow(), [hotkey("alt+space")]);
+        assert_eq!(hotkeys.mode(hotkey("alt+space").id()), Some(Mode::Search));
+        assert_eq!(hotkeys.mode(hotkey("cmd+shift+v").id()), None);
+    }
+
+    #[test]
+    fn paused_hotkeys_are_registered_on_resume() {
+        let registrar = FakeRegistrar::default();
+        let mut hotkeys = Hotkeys::with_registrar(registrar.clone());
+        hotkeys
+            .apply(&config(&[("alt+space", Mode::Search)]))
+            .unwrap();
+
+        hotkeys.pause();
+        assert!(hotkeys.is_paused());
+        assert!(registrar.registered.borrow().is_empty());
+        hotkeys
+            .apply(&config(&[("ctrl+space", Mode::Files)]))
+            .unwrap();
+        assert!(registrar.registered.borrow().is_empty());
+        assert_eq!(hotkeys.mode(hotkey("ctrl+space").id()), None);
+
+        hotkeys
+            .resume(&config(&[("ctrl+space", Mode::Files)]))
+            .unwrap();
+        assert!(!hotkeys.is_paused());
+        assert_eq!(*registrar.registered.borrow(), [hotkey("ctrl+space")]);
+    }
+
+    #[test]
+    fn errors_say_who_has_the_chord() {
+        let error = |source| {
+            HotkeyError {
+                chord: "alt+space".to_string(),
+                source,
+            }
+            .to_string()
+        };
+        assert_eq!(
+            error(global_hotkey::Error::AlreadyRegistered(hotkey("alt+space"))),
+            "hotkey \"alt+space\" is already registered by halcyon"
+        );
+        assert_eq!(
+            error(global_hotkey::Error::FailedToRegister(
+                "RegisterEventHotKey failed for Space".to_string()
+            )),
+            "hotkey \"alt+space\" couldn't be registered, it may be taken by another \
+             application (RegisterEventHotKey failed for Space)"
+        );
+    }
+}
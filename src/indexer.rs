@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedReceiver},
+};
+use gpui::{App, AsyncApp, Global};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::search::{MemoryProvider, Search, SearchItem, SearchProvider};
+
+// crawls a set of roots for app bundles and executables, for machines without spotlight
+#[derive(Clone)]
+pub struct Indexer {
+    roots: Arc<Vec<PathBuf>>,
+    max_depth: usize,
+    index: Arc<RwLock<HashMap<PathBuf, Vec<SearchItem>>>>,
+}
+
+impl Global for Indexer {}
+
+// keeps the active indexer's watcher alive, replacing the global drops the previous one and
+// with it the previous rescan loop
+pub struct IndexWatcher(Option<RecommendedWatcher>);
+
+impl Global for IndexWatcher {}
+
+impl Indexer {
+    pub const MAX_DEPTH: usize = 3;
+
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Self {
+            roots: Arc::new(roots),
+            max_depth: Self::MAX_DEPTH,
+            index: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    // replaces any running indexer with a fresh one over `roots`
+    pub fn start(roots: Vec<PathBuf>, cx: &mut App) {
+        let indexer = Indexer::new(roots);
+        let watcher = match indexer.watch() {
+            Ok((watcher, changes)) => {
+                indexer.rescan_changes(changes, cx);
+                Some(watcher)
+            }
+            Err(err) => {
+                eprintln!("failed to watch application roots: {err}");
+                None
+            }
+        };
+        cx.set_global(IndexWatcher(watcher));
+        cx.background_spawn({
+            let indexer = indexer.clone();
+            async move { indexer.rebuild() }
+        })
+        .detach();
+        cx.set_global(indexer);
+    }
+
+    pub fn default_roots() -> Vec<PathBuf> {
+        let mut roots = vec![
+            PathBuf::from("/Applications"),
+            PathBuf::from("/System/Applications"),
+        ];
+        if let Some(home) = std::env::var_os("HOME") {
+            roots.push(Path::new(&home).join("Applications"));
+        }
+        roots
+    }
+
+    #[cfg(test)]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn rebuild(&self) {
+        for root in self.roots.iter() {
+            self.rescan(root);
+        }
+    }
+
+    pub fn rescan(&self, root: &Path) {
+        let mut items = Vec::new();
+        crawl(root, self.max_depth, &mut items);
+        self.index
+            .write()
+            .unwrap()
+            .insert(root.to_path_buf(), items);
+    }
+
+    pub fn items(&self) -> Vec<SearchItem> {
+        let index = self.index.read().unwrap();
+        // roots are kept in their configured order so results are stable
+        self.roots
+            .iter()
+            .filter_map(|root| index.get(root))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    pub fn provider(&self) -> IndexProvider {
+        IndexProvider {
+            indexer: self.clone(),
+        }
+    }
+
+    // sends the root under each changed path, the returned watcher stops watching when dropped
+    pub fn watch(&self) -> notify::Result<(RecommendedWatcher, UnboundedReceiver<PathBuf>)> {
+        let (tx, rx) = mpsc::unbounded();
+        let roots = self.roots.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            for root in roots.iter() {
+                if event.paths.iter().any(|path| path.starts_with(root)) {
+                    let _ = tx.unbounded_send(root.clone());
+                }
+            }
+        })?;
+
+        for root in self.roots.iter().filter(|root| root.is_dir()) {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+        Ok((watcher, rx))
+    }
+
+    // installing an app is a burst of events across its bundle, they're left to settle and
+    // each root that changed is crawled once, off the main thread
+    fn rescan_changes(&self, mut changes: UnboundedReceiver<PathBuf>, cx: &App) {
+        let indexer = self.clone();
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            while let Some(root) = changes.next().await {
+                cx.background_executor()
+                    .timer(Duration::from_millis(100))
+                    .await;
+                let mut roots = HashSet::from([root]);
+                while let Ok(Some(root)) = changes.try_next() {
+                    roots.insert(root);
+                }
+
+                let indexer = indexer.clone();
+                cx.background_executor()
+                    .spawn(async move {
+                        for root in roots {
+                            indexer.rescan(&root);
+                        }
+                    })
+                    .await;
+            }
+        })
+        .detach();
+    }
+}
+
+pub struct IndexProvider {
+    indexer: Indexer,
+}
+
+impl SearchProvider for IndexProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        MemoryProvider::new(self.indexer.items()).search(query)
+    }
+}
+
+pub fn crawl(dir: &Path, depth: usize, items: &mut Vec<SearchItem>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        // follow symlinks, /Applications is full of them
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if let Some(stem) = name.strip_suffix(".app") {
+                items.push(SearchItem::file(stem, path));
+            } else if depth > 1 {
+                crawl(&path, depth - 1, items);
+            }
+        } else if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+            items.push(SearchItem::file(name, path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Target;
+
+    fn touch(path: &Path, mode: u32) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    fn names(indexer: &Indexer) -> Vec<String> {
+        let mut names: Vec<String> = indexer.items().into_iter().map(|item| item.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn finds_bundles_and_executables() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("Safari.app/Contents/MacOS")).unwrap();
+        touch(&root.join("Safari.app/Contents/MacOS/Safari"), 0o755);
+        fs::create_dir_all(root.join("Utilities/Terminal.app")).unwrap();
+        fs::create_dir_all(root.join(".Hidden.app")).unwrap();
+        touch(&root.join("tool"), 0o755);
+        touch(&root.join("notes.txt"), 0o644);
+
+        let indexer = Indexer::new(vec![root.to_path_buf()]);
+        indexer.rebuild();
+        // bundles aren't looked into
+        assert_eq!(names(&indexer), ["Safari", "Terminal", "tool"]);
+        let safari = indexer
+            .items()
+            .into_iter()
+            .find(|item| item.name == "Safari")
+            .unwrap();
+        assert_eq!(safari.target, Target::Path(root.join("Safari.app")));
+    }
+
+    #[test]
+    fn stops_at_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/Shallow.app")).unwrap();
+        fs::create_dir_all(root.join("a/b/Deep.app")).unwrap();
+
+        let indexer = Indexer::new(vec![root.to_path_buf()]).with_max_depth(2);
+        indexer.rebuild();
+        assert_eq!(names(&indexer), ["Shallow"]);
+        let indexer = Indexer::new(vec![root.to_path_buf()]).with_max_depth(3);
+        indexer.rebuild();
+        assert_eq!(names(&indexer), ["Deep", "Shallow"]);
+    }
+
+    #[test]
+    fn keeps_roots_in_order_and_rescans_one() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::create_dir_all(first.path().join("Zed.app")).unwrap();
+        fs::create_dir_all(second.path().join("Arc.app")).unwrap();
+
+        let roots = vec![first.path().to_path_buf(), second.path().to_path_buf()];
+        let indexer = Indexer::new(roots.clone());
+        assert_eq!(indexer.roots(), roots);
+        indexer.rebuild();
+        let order: Vec<String> = indexer.items().into_iter().map(|item| item.name).collect();
+        assert_eq!(order, ["Zed", "Arc"]);
+
+        fs::create_dir_all(second.path().join("Bear.app")).unwrap();
+        indexer.rescan(second.path());
+        assert_eq!(names(&indexer), ["Arc", "Bear", "Zed"]);
+    }
+
+    #[test]
+    fn watch_sends_the_changed_root() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let indexer = Indexer::new(vec![
+            first.path().to_path_buf(),
+            second.path().to_path_buf(),
+        ]);
+        let (_watcher, mut changes) = indexer.watch().unwrap();
+
+        fs::create_dir_all(second.path().join("Bear.app/Contents")).unwrap();
+        let mut roots = HashSet::new();
+        for _ in 0..100 {
+            while let Ok(Some(root)) = changes.try_next() {
+                roots.insert(root);
+            }
+            if !roots.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(roots, HashSet::from([second.path().to_path_buf()]));
+    }
+
+    #[test]
+    fn provider_searches_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("Safari.app")).unwrap();
+        fs::create_dir_all(dir.path().join("Calculator.app")).unwrap();
+        let indexer = Indexer::new(vec![dir.path().to_path_buf()]);
+        indexer.rebuild();
+
+        let mut items = Vec::new();
+        indexer.provider().search("safari").poll(&mut items);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Safari");
+    }
+}
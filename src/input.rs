@@ -1,8 +1,8 @@
-use std::{ops::Range, time::Duration};
+use std::{ops::Range, rc::Rc, time::Duration};
 
 use gpui::{
     App, Application, AsyncApp, Bounds, ClipboardItem, Context, CursorStyle, ElementId,
-    ElementInputHandler, Entity, EntityInputHandler, FocusHandle, Focusable, GlobalElementId,
+    ElementInputHandler, Entity, EntityInputHandler, FocusHandle, Focusable, GlobalElementId, Hsla,
     KeyBinding, Keystroke, LayoutId, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent,
     PaintQuad, Pixels, Point, ScrollHandle, ShapedLine, SharedString, Style, TextRun,
     UTF16Selection, UnderlineStyle, Window, WindowBounds, WindowOptions, actions, black, div, fill,
@@ -30,6 +30,27 @@ actions!(
     ]
 );
 
+pub fn bind_keys(cx: &mut App) {
+    cx.bind_keys([
+        KeyBinding::new("backspace", Backspace, Some("TextInput")),
+        KeyBinding::new("delete", Delete, Some("TextInput")),
+        KeyBinding::new("left", Left, Some("TextInput")),
+        KeyBinding::new("right", Right, Some("TextInput")),
+        KeyBinding::new("shift-left", SelectLeft, Some("TextInput")),
+        KeyBinding::new("shift-right", SelectRight, Some("TextInput")),
+        KeyBinding::new("cmd-a", SelectAll, Some("TextInput")),
+        KeyBinding::new("home", Home, Some("TextInput")),
+        KeyBinding::new("end", End, Some("TextInput")),
+        KeyBinding::new("cmd-c", Copy, Some("TextInput")),
+        KeyBinding::new("cmd-v", Paste, Some("TextInput")),
+        KeyBinding::new("cmd-x", Cut, Some("TextInput")),
+        KeyBinding::new("cmd-ctrl-space", ShowCharacterPalette, Some("TextInput")),
+    ]);
+}
+
+// colors ranges of the content, e.g. a query's keywords
+pub type Highlighter = Rc<dyn Fn(&str) -> Vec<(Range<usize>, Hsla)>>;
+
 #[derive(Clone)]
 pub struct TextInput {
     focus_handle: FocusHandle,
@@ -39,6 +60,7 @@ pub struct TextInput {
     selected_range: Range<usize>,
     selection_reversed: bool,
     marked_range: Option<Range<usize>>,
+    highlighter: Option<Highlighter>,
     last_layout: Option<ShapedLine>,
     last_bounds: Option<Bounds<Pixels>>,
     is_selecting: bool,
@@ -47,31 +69,16 @@ pub struct TextInput {
 }
 
 impl TextInput {
-    pub fn new(cx: &mut App) -> Self {
-        cx.bind_keys([
-            KeyBinding::new("backspace", Backspace, Some("TextInput")),
-            KeyBinding::new("delete", Delete, Some("TextInput")),
-            KeyBinding::new("left", Left, Some("TextInput")),
-            KeyBinding::new("right", Right, Some("TextInput")),
-            KeyBinding::new("shift-left", SelectLeft, Some("TextInput")),
-            KeyBinding::new("shift-right", SelectRight, Some("TextInput")),
-            KeyBinding::new("cmd-a", SelectAll, Some("TextInput")),
-            KeyBinding::new("home", Home, Some("TextInput")),
-            KeyBinding::new("end", End, Some("TextInput")),
-            KeyBinding::new("cmd-c", Copy, Some("TextInput")),
-            KeyBinding::new("cmd-v", Paste, Some("TextInput")),
-            KeyBinding::new("cmd-x", Cut, Some("TextInput")),
-            KeyBinding::new("cmd-ctrl-space", ShowCharacterPalette, Some("TextInput")),
-        ]);
-
+    pub fn new(cx: &mut App, placeholder: impl Into<SharedString>) -> Self {
         Self {
             focus_handle: cx.focus_handle(),
             scroll_handle: ScrollHandle::new(),
             content: SharedString::new(""),
-            placeholder: SharedString::new("Halcyon"),
+            placeholder: placeholder.into(),
             selected_range: 0..0,
             selection_reversed: false,
             marked_range: None,
+            highlighter: None,
             last_layout: None,
             last_bounds: None,
             is_selecting: false,
@@ -84,6 +91,30 @@ impl TextInput {
         &self.content
     }
 
+    // replaces the whole text, leaving the cursor at the end
+    pub fn set_content(&mut self, content: impl Into<SharedString>, cx: &mut Context<Self>) {
+        self.content = content.into();
+        self.selected_range = self.content.len()..self.content.len();
+        self.selection_reversed = false;
+        self.marked_range = None;
+        self.pause_blink();
+        cx.notify();
+    }
+
+    pub fn set_placeholder(
+        &mut self,
+        placeholder: impl Into<SharedString>,
+        cx: &mut Context<Self>,
+    ) {
+        self.placeholder = placeholder.into();
+        cx.notify();
+    }
+
+    pub fn set_highlighter(&mut self, highlighter: Highlighter, cx: &mut Context<Self>) {
+        self.highlighter = Some(highlighter);
+        cx.notify();
+    }
+
     pub fn toggle_cursor(&mut self) {
         self.cursor_visible = !self.cursor_visible;
     }
@@ -310,18 +341,11 @@ impl TextInput {
     }
 
     fn previous_boundary(&self, offset: usize) -> usize {
-        self.content
-            .grapheme_indices(true)
-            .rev()
-            .find_map(|(idx, _)| (idx < offset).then_some(idx))
-            .unwrap_or(0)
+        previous_boundary(&self.content, offset)
     }
 
     fn next_boundary(&self, offset: usize) -> usize {
-        self.content
-            .grapheme_indices(true)
-            .find_map(|(idx, _)| (idx > offset).then_some(idx))
-            .unwrap_or(self.content.len())
+        next_boundary(&self.content, offset)
     }
 
     fn reset(&mut self) {
@@ -335,6 +359,19 @@ impl TextInput {
     }
 }
 
+pub fn previous_boundary(text: &str, offset: usize) -> usize {
+    text.grapheme_indices(true)
+        .rev()
+        .find_map(|(idx, _)| (idx < offset).then_some(idx))
+        .unwrap_or(0)
+}
+
+pub fn next_boundary(text: &str, offset: usize) -> usize {
+    text.grapheme_indices(true)
+        .find_map(|(idx, _)| (idx > offset).then_some(idx))
+        .unwrap_or(text.len())
+}
+
 impl EntityInputHandler for TextInput {
     fn text_for_range(
         &mut self,
@@ -545,6 +582,22 @@ impl Element for TextElement {
             .into_iter()
             .filter(|run| run.len > 0)
             .collect()
+        } else if let Some(highlighter) = input.highlighter.as_ref()
+            && !input.content.is_empty()
+        {
+            let highlights: Vec<_> = highlighter(&display_text)
+                .into_iter()
+                .map(|(range, color)| {
+                    (
+                        range,
+                        TextRun {
+                            color,
+                            ..run.clone()
+                        },
+                    )
+                })
+                .collect();
+            highlight_runs(&display_text, &highlights, &run)
         } else {
             vec![run]
         };
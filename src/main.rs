@@ -1,18 +1,52 @@
-use std::time::Duration;
-
-use global_hotkey::{
-    GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState,
-    hotkey::{HotKey, Modifiers},
-};
-use gpui::{App, AppContext, Application, AsyncApp, KeyBinding};
+use gpui::{App, AppContext, Application};
 use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy};
 use objc2_foundation::MainThreadMarker;
 
-use crate::panel::Panel;
+use crate::{
+    action::{Launcher, SystemLauncher},
+    clipboard::ClipboardHistory,
+    config::Config,
+    history::History,
+    hotkey::Hotkeys,
+    indexer::Indexer,
+    mode::Mode,
+    panel::Panel,
+    pasteboard::MacPasteboard,
+    script::Scripts,
+    shell::CommandHistory,
+    snippet::Snippets,
+    storage::Storage,
+    tray::Tray,
+};
 
+mod action;
+mod calc;
+mod clipboard;
+mod config;
+mod datetime;
+mod dispatch;
+mod files;
+mod fuzzy;
+mod highlight;
+mod history;
+mod hotkey;
+mod indexer;
 mod input;
+mod mode;
 mod panel;
+mod pasteboard;
+mod query;
+mod reload;
+mod script;
+mod search;
+mod shell;
+mod snippet;
+mod spotlight;
+mod storage;
+mod tray;
+mod units;
 mod view;
+mod web;
 
 fn main() {
     Application::new().run(|cx: &mut App| {
@@ -22,34 +56,63 @@ fn main() {
             app.setActivationPolicy(NSApplicationActivationPolicy::Accessory);
         }
 
-        let panel = cx.new(|cx| Panel::new(cx));
+        let config = Config::load(&Config::path()).unwrap_or_else(|err| {
+            eprintln!("{err}, falling back to the default config");
+            Config::default()
+        });
 
-        // global shortcut handling
-        let hotkey_manager = Box::leak(Box::new(
-            GlobalHotKeyManager::new().expect("failed to create global hotkey manager"),
+        // built-in index, for when spotlight indexing is disabled
+        Indexer::start(config.search.index_roots.clone(), cx);
+
+        // the key is only fetched on first use, which may prompt for keychain access
+        let storage = Storage::new(&config.storage);
+        cx.set_global(storage.clone());
+        cx.set_global(History::load(History::default_path(), storage.clone()));
+        cx.set_global(CommandHistory::load(
+            CommandHistory::default_path(),
+            storage.clone(),
         ));
-        let hotkey = HotKey::new(Some(Modifiers::ALT), global_hotkey::hotkey::Code::Space);
-        hotkey_manager
-            .register(hotkey)
-            .expect("failed to register global hotkey");
-        let receiver = GlobalHotKeyEvent::receiver().clone();
-        let panel_for_hotkey = panel.clone();
-
-        cx.spawn({
-            async move |cx: &mut AsyncApp| {
-                loop {
-                    while let Ok(event) = receiver.try_recv() {
-                        if event.state == HotKeyState::Pressed {
-                            let _ = panel_for_hotkey.update(cx, |panel, cx| panel.toggle(cx));
-                        }
-                    }
-
-                    cx.background_executor()
-                        .timer(Duration::from_millis(20))
-                        .await;
-                }
-            }
+        let clipboard = ClipboardHistory::load(ClipboardHistory::default_path(), storage);
+        cx.background_spawn({
+            let clipboard = clipboard.clone();
+            async move { clipboard.migrate_images() }
         })
         .detach();
+        cx.set_global(clipboard);
+        clipboard::watch(MacPasteboard, &config.clipboard, cx);
+
+        let snippets = Snippets::load(Snippets::default_path());
+        reload::watch_snippets(snippets.clone(), cx);
+        cx.set_global(snippets);
+
+        let scripts = Scripts::load(Scripts::default_dir());
+        reload::watch_scripts(scripts.clone(), cx);
+        cx.set_global(scripts);
+
+        input::bind_keys(cx);
+        view::bind_keys(cx, &config.keys);
+
+        // global shortcut handling
+        let mut hotkeys = Hotkeys::new().expect("failed to create global hotkey manager");
+        if let Err(err) = hotkeys.apply(&config.hotkeys) {
+            // keep running, fixing the config registers them without a restart
+            eprintln!("{err}");
+            if let Err(err) =
+                SystemLauncher.notify("halcyon hotkey not registered", &err.to_string())
+            {
+                eprintln!("failed to show notification: {err}");
+            }
+        }
+        cx.set_global(hotkeys);
+        cx.set_global(config);
+
+        let panel = cx.new(|cx| Panel::new(Mode::Search, cx));
+        reload::watch(panel.clone(), cx);
+
+        dispatch::start(panel, cx);
+        match Tray::new() {
+            Ok(tray) => cx.set_global(tray),
+            Err(err) => eprintln!("failed to create tray icon: {err}"),
+        }
     });
 }
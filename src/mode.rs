@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+// what the panel is opened for, each global hotkey is bound to one of these
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Search,
+    Clipboard,
+    Calculator,
+    Snippets,
+    Files,
+    // search, starting from whatever is on the clipboard
+    PasteClipboard,
+}
+
+impl Mode {
+    // `None` means the configured placeholder
+    pub fn placeholder(self) -> Option<&'static str> {
+        match self {
+            Mode::Search | Mode::PasteClipboard => None,
+            Mode::Clipboard => Some("Clipboard history"),
+            Mode::Calculator => Some("Calculator"),
+            Mode::Snippets => Some("Snippets"),
+            Mode::Files => Some("Files"),
+        }
+    }
+
+    // modes that list everything before anything is typed
+    pub fn lists_when_empty(self) -> bool {
+        matches!(self, Mode::Clipboard | Mode::Snippets)
+    }
+}
@@ -1,27 +1,25 @@
 use dispatch2::run_on_main;
 use gpui::{
-    App, AppContext, Bounds, Focusable, WindowBackgroundAppearance, WindowBounds, WindowHandle,
-    WindowKind, WindowOptions, point, px, size,
+    App, AppContext, Bounds, Context, Focusable, WindowBackgroundAppearance, WindowBounds,
+    WindowHandle, WindowKind, WindowOptions, point, px, size,
 };
 use objc2_app_kit::NSEvent;
 
-use crate::view::View;
+use crate::{config::Config, mode::Mode, view::View};
 
 pub struct Panel {
     window: WindowHandle<View>,
 }
 
 impl Panel {
-    pub const WIDTH: f32 = 650.0;
-    pub const HEIGHT: f32 = 75.0;
-    pub const EXPANDED_HEIGHT: f32 = 300.0;
-
-    pub fn new(cx: &mut App) -> Self {
-        let window = Self::open_window(cx);
+    pub fn new(mode: Mode, cx: &mut Context<Self>) -> Self {
+        let window = Self::open_window(mode, cx);
         Self { window }
     }
 
-    fn open_window(cx: &mut App) -> WindowHandle<View> {
+    fn open_window(mode: Mode, cx: &mut Context<Self>) -> WindowHandle<View> {
+        let panel = cx.weak_entity();
+        let config = cx.global::<Config>().panel.clone();
         let mouse_pos = run_on_main(|_mtm| unsafe { NSEvent::mouseLocation() });
 
         let displays = cx.displays();
@@ -33,18 +31,23 @@ impl Panel {
                 && mouse_pos.y <= (bounds.origin.y + bounds.size.height).to_f64()
         });
 
+        let height = if mode.lists_when_empty() {
+            config.expanded_height
+        } else {
+            config.height
+        };
         let bounds = if let Some(display) = active {
             // appkit gives relative to bottom of screen, gpui expects relative to top of screen
             let bounds = display.bounds();
             Bounds::new(
                 point(
-                    bounds.center().x - px(Self::WIDTH / 2.0),
-                    bounds.size.height * 0.2,
+                    bounds.center().x - px(config.width / 2.0),
+                    bounds.size.height * config.vertical_offset,
                 ),
-                size(px(Self::WIDTH), px(Self::HEIGHT)),
+                size(px(config.width), px(height)),
             )
         } else {
-            Bounds::centered(None, size(px(Self::WIDTH), px(Self::HEIGHT)), cx)
+            Bounds::centered(None, size(px(config.width), px(height)), cx)
         };
 
         let window = cx
@@ -64,7 +67,7 @@ impl Panel {
                 },
                 move |window, cx| {
                     cx.new(|cx| {
-                        let view = View::new(cx, window);
+                        let view = View::new(cx, window, panel, mode);
                         view
                     })
                 },
@@ -80,6 +83,19 @@ impl Panel {
         window
     }
 
+    // re-applies the configured geometry to an open panel
+    pub fn resize(&mut self, cx: &mut App) {
+        let config = cx.global::<Config>().panel.clone();
+        let _ = self.window.update(cx, |view, window, cx| {
+            let height = if view.is_collapsed(cx) {
+                config.height
+            } else {
+                config.expanded_height
+            };
+            window.resize(size(px(config.width), px(height)));
+        });
+    }
+
     pub fn hide(&mut self, cx: &mut App) {
         let _ = self.window.update(cx, |_view, window, cx| {
             cx.hide();
@@ -87,15 +103,36 @@ impl Panel {
         });
     }
 
-    pub fn show(&mut self, cx: &mut App) {
-        *self = Self::new(cx);
+    pub fn show(&mut self, mode: Mode, cx: &mut Context<Self>) {
+        *self = Self::new(mode, cx);
+    }
+
+    // the mode the panel is showing in, if it's open
+    pub fn open_mode(&self, cx: &App) -> Option<Mode> {
+        self.window
+            .is_active(cx)
+            .and_then(|_| self.window.read(cx).ok())
+            .map(|view| view.mode())
+    }
+
+    // shows the panel in `mode`, switching over if it's open in another one
+    pub fn open(&mut self, mode: Mode, cx: &mut Context<Self>) {
+        match self.open_mode(cx) {
+            Some(open_mode) if open_mode == mode => {}
+            Some(_) => {
+                self.hide(cx);
+                self.show(mode, cx);
+            }
+            None => self.show(mode, cx),
+        }
     }
 
-    pub fn toggle(&mut self, cx: &mut App) {
-        if let Some(_) = self.window.is_active(cx) {
+    // a hotkey for another mode switches the open panel over instead of hiding it
+    pub fn toggle(&mut self, mode: Mode, cx: &mut Context<Self>) {
+        if self.open_mode(cx) == Some(mode) {
             self.hide(cx);
         } else {
-            self.show(cx);
+            self.open(mode, cx);
         }
     }
 }
@@ -0,0 +1,115 @@
+use std::{io, path::PathBuf};
+
+use objc2::runtime::ProtocolObject;
+use objc2_app_kit::{
+    NSPasteboard, NSPasteboardItem, NSPasteboardTypeFileURL, NSPasteboardTypePNG,
+    NSPasteboardTypeString, NSPasteboardTypeTIFF, NSPasteboardTypeURL, NSPasteboardWriting,
+    NSWorkspace,
+};
+use objc2_foundation::{NSArray, NSData, NSString};
+use url::Url;
+
+use crate::clipboard::{Contents, Pasteboard};
+
+// the general pasteboard, appkit expects it to be used from the main thread
+pub struct MacPasteboard;
+
+impl Pasteboard for MacPasteboard {
+    fn change_count(&self) -> i64 {
+        unsafe { NSPasteboard::generalPasteboard().changeCount() as i64 }
+    }
+
+    fn types(&self) -> Vec<String> {
+        unsafe { NSPasteboard::generalPasteboard().types() }
+            .map(|types| types.iter().map(|kind| kind.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    // there's no record of who copied, but it's almost always the application in front
+    fn source_app(&self) -> Option<String> {
+        let app = unsafe { NSWorkspace::sharedWorkspace().frontmostApplication() }?;
+        unsafe { app.bundleIdentifier() }.map(|id| id.to_string())
+    }
+
+    // copies usually carry several types, e.g. copied files have their names as text too, so
+    // the most specific one wins
+    fn read(&self) -> Option<Contents> {
+        unsafe {
+            let pasteboard = NSPasteboard::generalPasteboard();
+
+            let files: Vec<PathBuf> = pasteboard
+                .pasteboardItems()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.stringForType(NSPasteboardTypeFileURL))
+                        .filter_map(|url| Url::parse(&url.to_string()).ok()?.to_file_path().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !files.is_empty() {
+                return Some(Contents::Files(files));
+            }
+            if let Some(url) = pasteboard.stringForType(NSPasteboardTypeURL) {
+                return Some(Contents::Url(url.to_string()));
+            }
+            if let Some(text) = pasteboard.stringForType(NSPasteboardTypeString) {
+                return Some(Contents::Text(text.to_string()));
+            }
+            [NSPasteboardTypePNG, NSPasteboardTypeTIFF]
+                .into_iter()
+                .find_map(|uti| {
+                    let data = pasteboard.dataForType(uti)?;
+                    Some(Contents::Image {
+                        uti: uti.to_string(),
+                        bytes: data.to_vec(),
+                    })
+                })
+        }
+    }
+
+    fn write(&self, contents: &Contents) -> io::Result<()> {
+        let written = unsafe {
+            let pasteboard = NSPasteboard::generalPasteboard();
+            pasteboard.clearContents();
+            match contents {
+                Contents::Text(text) => {
+                    pasteboard.setString_forType(&NSString::from_str(text), NSPasteboardTypeString)
+                }
+                Contents::Url(url) => {
+                    let url = NSString::from_str(url);
+                    pasteboard.setString_forType(&url, NSPasteboardTypeURL)
+                        && pasteboard.setString_forType(&url, NSPasteboardTypeString)
+                }
+                // one item per file, the way finder copies them
+                Contents::Files(paths) => {
+                    let items: Vec<_> = paths
+                        .iter()
+                        .filter_map(|path| Url::from_file_path(path).ok())
+                        .map(|url| {
+                            let item = NSPasteboardItem::new();
+                            item.setString_forType(
+                                &NSString::from_str(url.as_str()),
+                                NSPasteboardTypeFileURL,
+                            );
+                            item
+                        })
+                        .collect();
+                    let objects: Vec<&ProtocolObject<dyn NSPasteboardWriting>> = items
+                        .iter()
+                        .map(|item| ProtocolObject::from_ref(&**item))
+                        .collect();
+                    pasteboard.writeObjects(&NSArray::from_slice(&objects))
+                }
+                Contents::Image { uti, bytes } => pasteboard
+                    .setData_forType(Some(&NSData::with_bytes(bytes)), &NSString::from_str(uti)),
+            }
+        };
+
+        if written {
+            Ok(())
+        } else {
+            Err(io::Error::other("failed to write to pasteboard"))
+        }
+    }
+}
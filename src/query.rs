@@ -0,0 +1,489 @@
+use std::ops::Range;
+
+// text from the query, unquoted and unescaped, with the byte range it was typed at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Text {
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+// a leading keyword that hands the query to something else, e.g. `=` for the calculator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    pub keyword: String,
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    Word(Text),
+    // in double quotes, so it can hold whitespace
+    Phrase(Text),
+    // `key:value`, for one of the parser's keys
+    Filter { key: Text, value: Text },
+    // `-term`, `span` covers the dash and the term
+    Not { term: Box<Term>, span: Range<usize> },
+}
+
+impl Term {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Term::Word(text) | Term::Phrase(text) => text.span.clone(),
+            Term::Filter { key, value } => key.span.start..value.span.end,
+            Term::Not { span, .. } => span.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query {
+    pub trigger: Option<Trigger>,
+    // everything after the trigger as typed, for triggers that want it verbatim, like `= 2 - 1`
+    pub rest: Text,
+    pub terms: Vec<Term>,
+}
+
+// what the input colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Trigger,
+    // with its colon
+    Key,
+    // the dash
+    Negation,
+}
+
+impl Query {
+    pub fn highlights(&self) -> Vec<(Range<usize>, Highlight)> {
+        fn add(term: &Term, highlights: &mut Vec<(Range<usize>, Highlight)>) {
+            match term {
+                Term::Word(_) | Term::Phrase(_) => {}
+                Term::Filter { key, .. } => {
+                    highlights.push((key.span.start..key.span.end + 1, Highlight::Key));
+                }
+                Term::Not { term: inner, span } => {
+                    highlights.push((span.start..span.start + 1, Highlight::Negation));
+                    add(inner, highlights);
+                }
+            }
+        }
+
+        let mut highlights = Vec::new();
+        if let Some(trigger) = &self.trigger {
+            highlights.push((trigger.span.clone(), Highlight::Trigger));
+        }
+        for term in &self.terms {
+            add(term, &mut highlights);
+        }
+        highlights
+    }
+}
+
+// whitespace separates terms, a backslash escapes the character after it and quotes only
+// start a phrase at the beginning of a term
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Parser {
+    triggers: Vec<String>,
+    // other `key:value` words are just words, e.g. "notes:draft"
+    keys: Vec<String>,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_triggers(mut self, triggers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.triggers.extend(
+            triggers
+                .into_iter()
+                .map(Into::into)
+                .filter(|t| !t.is_empty()),
+        );
+        self
+    }
+
+    pub fn with_keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.keys.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> Query {
+        let trigger = self.trigger(input);
+        let start = trigger.as_ref().map_or(0, |trigger| trigger.span.end);
+        let rest_start = skip_whitespace(input, start);
+
+        let mut terms = Vec::new();
+        let mut pos = rest_start;
+        while pos < input.len() {
+            let (term, end) = self.term(input, pos);
+            terms.push(term);
+            pos = skip_whitespace(input, end);
+        }
+
+        Query {
+            trigger,
+            rest: Text {
+                text: input[rest_start..].to_string(),
+                span: rest_start..input.len(),
+            },
+            terms,
+        }
+    }
+
+    // the longest trigger the input starts with, keywords ending in a letter or digit need a
+    // space after them so `g foo` triggers `g` but `google` doesn't
+    pub fn trigger(&self, input: &str) -> Option<Trigger> {
+        let start = skip_whitespace(input, 0);
+        let text = &input[start..];
+        self.triggers
+            .iter()
+            .filter(|keyword| {
+                let Some(after) = text.strip_prefix(keyword.as_str()) else {
+                    return false;
+                };
+                !keyword.ends_with(char::is_alphanumeric) || after.starts_with(char::is_whitespace)
+            })
+            .max_by_key(|keyword| keyword.len())
+            .map(|keyword| Trigger {
+                keyword: keyword.clone(),
+                span: start..start + keyword.len(),
+            })
+    }
+
+    // the term at `start`, which isn't whitespace, and where it ends
+    fn term(&self, input: &str, start: usize) -> (Term, usize) {
+        let rest = &input[start..];
+        if let Some(after) = rest.strip_prefix('-')
+            && after.starts_with(|c: char| !c.is_whitespace())
+        {
+            let (term, end) = self.term(input, start + 1);
+            let span = start..end;
+            return (
+                Term::Not {
+                    term: Box::new(term),
+                    span,
+                },
+                end,
+            );
+        }
+        if rest.starts_with('"') {
+            let (text, end) = phrase(input, start);
+            return (Term::Phrase(text), end);
+        }
+
+        let key_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_len];
+        if key_len > 0
+            && rest[key_len..].starts_with(':')
+            && self
+                .keys
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(key))
+        {
+            let key = Text {
+                text: key.to_lowercase(),
+                span: start..start + key_len,
+            };
+            let value_start = start + key_len + 1;
+            let (value, end) = if input[value_start..].starts_with('"') {
+                phrase(input, value_start)
+            } else {
+                word(input, value_start)
+            };
+            return (Term::Filter { key, value }, end);
+        }
+
+        let (text, end) = word(input, start);
+        (Term::Word(text), end)
+    }
+}
+
+fn skip_whitespace(input: &str, pos: usize) -> usize {
+    input.len() - input[pos..].trim_start().len()
+}
+
+// up to the next whitespace that isn't escaped
+fn word(input: &str, start: usize) -> (Text, usize) {
+    let mut text = String::new();
+    let mut end = input.len();
+    let mut chars = input[start..].char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                end = start + idx;
+                break;
+            }
+            '\\' => text.push(chars.next().map_or('\\', |(_, escaped)| escaped)),
+            c => text.push(c),
+        }
+    }
+    (
+        Text {
+            text,
+            span: start..end,
+        },
+        end,
+    )
+}
+
+// from the quote at `start` to the closing one, or the end of the input if there isn't one
+fn phrase(input: &str, start: usize) -> (Text, usize) {
+    let mut text = String::new();
+    let mut end = input.len();
+    let mut chars = input[start + 1..].char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => {
+                end = start + 1 + idx + 1;
+                break;
+            }
+            '\\' => text.push(chars.next().map_or('\\', |(_, escaped)| escaped)),
+            c => text.push(c),
+        }
+    }
+    (
+        Text {
+            text,
+            span: start..end,
+        },
+        end,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the empty trigger is ignored, it would match everything
+    fn parser() -> Parser {
+        Parser::new()
+            .with_triggers(["'", "/", "=", "g", "gh", ""])
+            .with_keys(["kind", "in", "content"])
+    }
+
+    // each term as a short string, with the text it was typed as
+    fn terms(input: &str) -> Vec<(String, &str)> {
+        fn describe(term: &Term) -> String {
+            match term {
+                Term::Word(text) => format!("word {}", text.text),
+                Term::Phrase(text) => format!("phrase {}", text.text),
+                Term::Filter { key, value } => format!("{}={}", key.text, value.text),
+                Term::Not { term, .. } => format!("not {}", describe(term)),
+            }
+        }
+        parser()
+            .parse(input)
+            .terms
+            .iter()
+            .map(|term| (describe(term), &input[term.span()]))
+            .collect()
+    }
+
+    fn describe_all(input: &str) -> Vec<String> {
+        terms(input).into_iter().map(|(term, _)| term).collect()
+    }
+
+    #[test]
+    fn triggers() {
+        let query = parser().parse("g foo  bar ");
+        assert_eq!(
+            query.trigger,
+            Some(Trigger {
+                keyword: "g".to_string(),
+                span: 0..1,
+            })
+        );
+        assert_eq!(query.rest.text, "foo  bar ");
+        assert_eq!(query.rest.span, 2..11);
+
+        // keywords ending in a letter need a space, others don't
+        assert_eq!(parser().parse("google").trigger, None);
+        assert_eq!(parser().parse("g").trigger, None);
+        assert_eq!(parser().parse("gh x").trigger.unwrap().keyword, "gh");
+        let query = parser().parse("=2+2");
+        assert_eq!(query.trigger.unwrap().span, 0..1);
+        assert_eq!(query.rest.text, "2+2");
+
+        let query = parser().parse("  'report");
+        assert_eq!(query.trigger.unwrap().span, 2..3);
+        assert_eq!(query.rest.span, 3..9);
+
+        // an escaped trigger is just text
+        let query = parser().parse("\\'x");
+        assert_eq!(query.trigger, None);
+        assert_eq!(describe_all("\\'x"), ["word 'x"]);
+        assert_eq!(parser().parse("").rest.span, 0..0);
+    }
+
+    #[test]
+    fn terms_and_spans() {
+        assert_eq!(
+            terms(r#"report -kind:pdf content:"two words" notes:draft KIND:x kind:"#),
+            [
+                ("word report".to_string(), "report"),
+                ("not kind=pdf".to_string(), "-kind:pdf"),
+                ("content=two words".to_string(), r#"content:"two words""#),
+                ("word notes:draft".to_string(), "notes:draft"),
+                ("kind=x".to_string(), "KIND:x"),
+                ("kind=".to_string(), "kind:"),
+            ]
+        );
+        // quotes only start a phrase at the start of a term
+        assert_eq!(
+            terms(r#"say"hi there" "a b"c"#),
+            [
+                ("word say\"hi".to_string(), "say\"hi"),
+                ("word there\"".to_string(), "there\""),
+                ("phrase a b".to_string(), "\"a b\""),
+                ("word c".to_string(), "c"),
+            ]
+        );
+        assert_eq!(
+            terms("--x -\"a b\""),
+            [
+                ("not not word x".to_string(), "--x"),
+                ("not phrase a b".to_string(), "-\"a b\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            terms(r#"a\ b C:\\x \"q\" "say \"hi\"" trail\"#),
+            [
+                ("word a b".to_string(), r"a\ b"),
+                ("word C:\\x".to_string(), r"C:\\x"),
+                ("word \"q\"".to_string(), r#"\"q\""#),
+                ("phrase say \"hi\"".to_string(), r#""say \"hi\"""#),
+                // nothing left to escape
+                ("word trail\\".to_string(), r"trail\"),
+            ]
+        );
+        assert_eq!(describe_all(r"\-x \kind:pdf"), ["word -x", "word kind:pdf"]);
+        assert_eq!(describe_all(r#""a\"#), ["phrase a\\"]);
+    }
+
+    #[test]
+    fn unclosed_quotes() {
+        assert_eq!(
+            terms(r#"x "two words \" still"#),
+            [
+                ("word x".to_string(), "x"),
+                (
+                    "phrase two words \" still".to_string(),
+                    r#""two words \" still"#
+                ),
+            ]
+        );
+        assert_eq!(terms("\""), [("phrase ".to_string(), "\"")]);
+        assert_eq!(
+            terms("in:\"/my docs"),
+            [("in=/my docs".to_string(), "in:\"/my docs")]
+        );
+        let query = parser().parse("-\"a");
+        assert_eq!(query.highlights(), [(0..1, Highlight::Negation)]);
+    }
+
+    #[test]
+    fn lone_dashes() {
+        assert_eq!(
+            terms("a - b -"),
+            [
+                ("word a".to_string(), "a"),
+                ("word -".to_string(), "-"),
+                ("word b".to_string(), "b"),
+                ("word -".to_string(), "-"),
+            ]
+        );
+        assert!(parser().parse("- -").highlights().is_empty());
+    }
+
+    #[test]
+    fn multibyte_input() {
+        let input = "  'café -kind:ü \"日本 語\" 🎉:x\u{3000}end";
+        let query = parser().parse(input);
+        assert_eq!(query.trigger.as_ref().unwrap().span, 2..3);
+        assert_eq!(
+            terms(input),
+            [
+                ("word café".to_string(), "café"),
+                ("not kind=ü".to_string(), "-kind:ü"),
+                ("phrase 日本 語".to_string(), "\"日本 語\""),
+                ("word 🎉:x".to_string(), "🎉:x"),
+                ("word end".to_string(), "end"),
+            ]
+        );
+        let start = input.find("-kind").unwrap();
+        assert_eq!(
+            query.highlights()[1..],
+            [
+                (start..start + 1, Highlight::Negation),
+                (start + 1..start + 6, Highlight::Key),
+            ]
+        );
+    }
+
+    #[test]
+    fn highlights() {
+        let input = r#"' report -kind:pdf content:"two words" -x"#;
+        assert_eq!(
+            parser().parse(input).highlights(),
+            [
+                (0..1, Highlight::Trigger),
+                (9..10, Highlight::Negation),
+                (10..15, Highlight::Key),
+                (19..27, Highlight::Key),
+                (39..40, Highlight::Negation),
+            ]
+        );
+        assert!(parser().parse("a b:c").highlights().is_empty());
+    }
+
+    // xorshift, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, below: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % below as u64) as usize
+        }
+    }
+
+    #[test]
+    fn never_panics() {
+        let pieces = [
+            "a", "G", " ", "\t", "\u{3000}", "-", "\"", "\\", ":", "=", "g", "gh", "'", "/",
+            "kind", "IN", "é", "日", "🎉", "\u{301}",
+        ];
+        let parser = parser();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..100_000 {
+            let len = rng.next(16);
+            let input: String = (0..len).map(|_| pieces[rng.next(pieces.len())]).collect();
+            let query = parser.parse(&input);
+
+            assert!(input.get(query.rest.span.clone()).is_some(), "{input:?}");
+            assert_eq!(query.rest.span.end, input.len());
+            // terms are in order, don't overlap and aren't empty
+            let mut end = query.rest.span.start;
+            for term in &query.terms {
+                let span = term.span();
+                assert!(span.start >= end && span.start < span.end, "{input:?}");
+                assert!(input.get(span.clone()).is_some(), "{input:?}");
+                end = span.end;
+            }
+            for (range, _) in query.highlights() {
+                assert!(
+                    input.get(range).is_some_and(|text| !text.is_empty()),
+                    "{input:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{error::Error, path::Path, time::Duration};
+
+use futures::StreamExt;
+use gpui::{App, AsyncApp, Entity};
+
+use crate::{
+    action::{Launcher, SystemLauncher},
+    clipboard,
+    config::Config,
+    hotkey::Hotkeys,
+    indexer::Indexer,
+    input,
+    panel::Panel,
+    pasteboard::MacPasteboard,
+    script::Scripts,
+    snippet::Snippets,
+    view,
+};
+
+// re-applies the config file whenever it changes, keeping the current config if it's invalid
+pub fn watch(panel: Entity<Panel>, cx: &mut App) {
+    let path = Config::path();
+    let (watcher, mut changes) = match Config::watch(&path) {
+        Ok(watch) => watch,
+        Err(err) => {
+            eprintln!("failed to watch {}: {err}", path.display());
+            return;
+        }
+    };
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let _watcher = watcher;
+        while changes.next().await.is_some() {
+            // a single save is usually several events, let them settle
+            cx.background_executor()
+                .timer(Duration::from_millis(100))
+                .await;
+            while let Ok(Some(())) = changes.try_next() {}
+
+            let _ = cx.update(|cx| reload(&path, &panel, cx));
+        }
+    })
+    .detach();
+}
+
+// same for the snippet library, which is its own file so it can grow without cluttering the
+// config
+pub fn watch_snippets(snippets: Snippets, cx: &mut App) {
+    let path = snippets.path().to_path_buf();
+    let (watcher, mut changes) = match Config::watch(&path) {
+        Ok(watch) => watch,
+        Err(err) => {
+            eprintln!("failed to watch {}: {err}", path.display());
+            return;
+        }
+    };
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let _watcher = watcher;
+        while changes.next().await.is_some() {
+            cx.background_executor()
+                .timer(Duration::from_millis(100))
+                .await;
+            while let Ok(Some(())) = changes.try_next() {}
+
+            if let Err(err) = snippets.reload() {
+                eprintln!("snippets not applied: {err}");
+                if let Err(err) =
+                    SystemLauncher.notify("halcyon snippets not applied", &err.to_string())
+                {
+                    eprintln!("failed to show notification: {err}");
+                }
+            }
+        }
+    })
+    .detach();
+}
+
+// and for the scripts directory, a broken script is left out rather than keeping the old set
+pub fn watch_scripts(scripts: Scripts, cx: &mut App) {
+    let dir = scripts.dir().to_path_buf();
+    let (watcher, mut changes) = match scripts.watch() {
+        Ok(watch) => watch,
+        Err(err) => {
+            eprintln!("failed to watch {}: {err}", dir.display());
+            return;
+        }
+    };
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let _watcher = watcher;
+        while changes.next().await.is_some() {
+            cx.background_executor()
+                .timer(Duration::from_millis(100))
+                .await;
+            while let Ok(Some(())) = changes.try_next() {}
+
+            let errors = scripts.reload();
+            if errors.is_empty() {
+                continue;
+            }
+            let message = errors
+                .iter()
+                .map(|err| err.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            eprintln!("scripts not loaded: {message}");
+            if let Err(err) = SystemLauncher.notify("halcyon scripts not loaded", &message) {
+                eprintln!("failed to show notification: {err}");
+            }
+        }
+    })
+    .detach();
+}
+
+fn reload(path: &Path, panel: &Entity<Panel>, cx: &mut App) {
+    let mut config = cx.global::<Config>().clone();
+    let old = match swap(path, &mut config, cx.global_mut::<Hotkeys>()) {
+        Ok(Some(old)) => old,
+        Ok(None) => return,
+        Err(err) => return reject(&err.to_string()),
+    };
+    if config.keys != old.keys {
+        cx.clear_key_bindings();
+        input::bind_keys(cx);
+        view::bind_keys(cx, &config.keys);
+    }
+    if config.search.index_roots != old.search.index_roots {
+        Indexer::start(config.search.index_roots.clone(), cx);
+    }
+    if config.clipboard != old.clipboard {
+        clipboard::watch(MacPasteboard, &config.clipboard, cx);
+    }
+    // the histories are already loaded with the old key, switching under them could lose data
+    if config.storage != old.storage {
+        let message = "storage settings take effect after halcyon restarts";
+        eprintln!("{message}");
+        if let Err(err) = SystemLauncher.notify("halcyon config changed", message) {
+            eprintln!("failed to show notification: {err}");
+        }
+    }
+
+    cx.set_global(config);
+    panel.update(cx, |panel, cx| panel.resize(cx));
+}
+
+// replaces `config` with the file's and registers its hotkeys, returning the previous config.
+// none when nothing changed, and on any error both are left as they were
+fn swap(
+    path: &Path,
+    config: &mut Config,
+    hotkeys: &mut Hotkeys,
+) -> Result<Option<Config>, Box<dyn Error>> {
+    let new = Config::load(path)?;
+    if new == *config {
+        return Ok(None);
+    }
+    hotkeys.apply(&new.hotkeys)?;
+    Ok(Some(std::mem::replace(config, new)))
+}
+
+fn reject(message: &str) {
+    eprintln!("config not applied: {message}");
+    if let Err(err) = SystemLauncher.notify("halcyon config not applied", message) {
+        eprintln!("failed to show notification: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use global_hotkey::hotkey::HotKey;
+
+    use super::*;
+    use crate::hotkey::FakeRegistrar;
+
+    fn hotkey(chord: &str) -> HotKey {
+        chord.parse().unwrap()
+    }
+
+    #[test]
+    fn invalid_edits_keep_the_previous_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let registrar = FakeRegistrar::default();
+        let mut hotkeys = Hotkeys::with_registrar(registrar.clone());
+        let mut config = Config::default();
+        hotkeys.apply(&config.hotkeys).unwrap();
+
+        fs::write(&path, "[panel]\nwidth = 800\n").unwrap();
+        let old = swap(&path, &mut config, &mut hotkeys).unwrap();
+        assert_eq!(old, Some(Config::default()));
+        assert_eq!(config.panel.width, 800.0);
+        // saved again without changes
+        assert_eq!(swap(&path, &mut config, &mut hotkeys).unwrap(), None);
+
+        let valid = config.clone();
+        for edit in [
+            "[panel]\nwidth = -1\n",
+            "[panel\nwidth = 700\n",
+            "[hotkeys]\n\"hyper+x\" = \"search\"\n",
+        ] {
+            fs::write(&path, edit).unwrap();
+            assert!(swap(&path, &mut config, &mut hotkeys).is_err(), "{edit}");
+            assert_eq!(config, valid, "{edit}");
+        }
+        assert_eq!(*registrar.registered.borrow(), [hotkey("alt+space")]);
+    }
+
+    #[test]
+    fn taken_hotkeys_keep_the_previous_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let registrar = FakeRegistrar {
+            taken: vec![hotkey("ctrl+space")],
+            ..Default::default()
+        };
+        let mut hotkeys = Hotkeys::with_registrar(registrar.clone());
+        let mut config = Config::default();
+        hotkeys.apply(&config.hotkeys).unwrap();
+
+        fs::write(&path, "[hotkeys]\n\"ctrl+space\" = \"search\"\n").unwrap();
+        assert!(swap(&path, &mut config, &mut hotkeys).is_err());
+        assert_eq!(config, Config::default());
+        assert_eq!(*registrar.registered.borrow(), [hotkey("alt+space")]);
+
+        fs::write(&path, "[hotkeys]\n\"cmd+space\" = \"search\"\n").unwrap();
+        assert!(swap(&path, &mut config, &mut hotkeys).unwrap().is_some());
+        assert_eq!(*registrar.registered.borrow(), [hotkey("cmd+space")]);
+    }
+}
@@ -0,0 +1,938 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::{self, Read},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use gpui::Global;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    config::{Config, expand_home},
+    query::{Parser, Term},
+    search::{MemoryProvider, Search, SearchItem, SearchProvider, Target},
+};
+
+// header lines look like `# @halcyon.title Open ticket`, after any comment marker
+const PREFIX: &str = "@halcyon.";
+const COMMENT_MARKERS: [&str; 4] = ["#", "//", "--", ";"];
+// only the top of a file is read for its header
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+
+// what happens to a script's stdout once it exits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    // streamed into the panel like a shell command's
+    #[default]
+    Inline,
+    // put on the clipboard, and the panel hides
+    Copy,
+    // a json list of rows to pick from, see `parse_rows`
+    Rows,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Argument {
+    pub name: String,
+    // optional ones can only come after the required ones
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub title: String,
+    // typed first with a space after, the rest of the query is the arguments
+    pub keyword: Option<String>,
+    pub description: Option<String>,
+    pub arguments: Vec<Argument>,
+    pub output: OutputMode,
+    pub timeout: Duration,
+}
+
+impl Manifest {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn required(&self) -> usize {
+        self.arguments
+            .iter()
+            .filter(|argument| !argument.optional)
+            .count()
+    }
+}
+
+// lines are numbered from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestError {
+    MissingTitle,
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+    Duplicate {
+        line: usize,
+        key: String,
+    },
+    Invalid {
+        line: usize,
+        key: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::MissingTitle => write!(f, "`{PREFIX}title` is missing"),
+            ManifestError::UnknownKey { line, key } => {
+                write!(f, "line {line}: unknown key `{PREFIX}{key}`")
+            }
+            ManifestError::Duplicate { line, key } => {
+                write!(f, "line {line}: `{PREFIX}{key}` is set twice")
+            }
+            ManifestError::Invalid { line, key, message } => {
+                write!(f, "line {line}: invalid `{PREFIX}{key}`: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+// `None` for a file without a header, e.g. a helper the scripts share:
+//
+//     #!/bin/sh
+//     # @halcyon.title Open ticket
+//     # @halcyon.keyword jira
+//     # @halcyon.description Opens a ticket by its id
+//     # @halcyon.argument id
+//     # @halcyon.argument project optional
+//     # @halcyon.output inline | copy | json
+//     # @halcyon.timeout 10s
+pub fn parse_manifest(text: &str) -> Result<Option<Manifest>, ManifestError> {
+    let mut found = false;
+    let mut title = None;
+    let mut keyword = None;
+    let mut description = None;
+    let mut arguments: Vec<Argument> = Vec::new();
+    let mut output = None;
+    let mut timeout = None;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // the header ends at the first line of code, the script's own comments can say anything
+        let Some(comment) = COMMENT_MARKERS
+            .iter()
+            .find_map(|marker| line.strip_prefix(marker))
+        else {
+            break;
+        };
+        let Some(line) = comment.trim_start().strip_prefix(PREFIX) else {
+            continue;
+        };
+        found = true;
+
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        let invalid = |message: &str| ManifestError::Invalid {
+            line: line_no,
+            key: key.to_string(),
+            message: message.to_string(),
+        };
+        // only `argument` can be repeated
+        let once = |set: bool| match set {
+            true => Err(ManifestError::Duplicate {
+                line: line_no,
+                key: key.to_string(),
+            }),
+            false => Ok(()),
+        };
+        match key {
+            "title" => {
+                once(title.is_some())?;
+                if value.is_empty() {
+                    return Err(invalid("can't be empty"));
+                }
+                title = Some(value.to_string());
+            }
+            "keyword" => {
+                once(keyword.is_some())?;
+                if value.is_empty() || value.contains(char::is_whitespace) {
+                    return Err(invalid("should be a single word"));
+                }
+                keyword = Some(value.to_string());
+            }
+            "description" => {
+                once(description.is_some())?;
+                description = Some(value.to_string());
+            }
+            "argument" => {
+                let argument = match value.split_whitespace().collect::<Vec<_>>()[..] {
+                    [name] => Argument {
+                        name: name.to_string(),
+                        optional: false,
+                    },
+                    [name, "optional"] => Argument {
+                        name: name.to_string(),
+                        optional: true,
+                    },
+                    _ => {
+                        return Err(invalid(
+                            "should be a name, optionally followed by `optional`",
+                        ));
+                    }
+                };
+                if arguments.iter().any(|other| other.name == argument.name) {
+                    return Err(invalid("names have to be unique"));
+                }
+                if !argument.optional && arguments.iter().any(|other| other.optional) {
+                    return Err(invalid("required arguments have to come first"));
+                }
+                arguments.push(argument);
+            }
+            "output" => {
+                once(output.is_some())?;
+                output = Some(match value {
+                    "inline" => OutputMode::Inline,
+                    "copy" => OutputMode::Copy,
+                    "json" => OutputMode::Rows,
+                    _ => return Err(invalid("should be `inline`, `copy` or `json`")),
+                });
+            }
+            "timeout" => {
+                once(timeout.is_some())?;
+                let seconds: u64 = value
+                    .strip_suffix('s')
+                    .unwrap_or(value)
+                    .parse()
+                    .map_err(|_| invalid("should be a number of seconds, like `10s`"))?;
+                if seconds == 0 {
+                    return Err(invalid("must be at least 1s"));
+                }
+                timeout = Some(Duration::from_secs(seconds));
+            }
+            _ => {
+                return Err(ManifestError::UnknownKey {
+                    line: line_no,
+                    key: key.to_string(),
+                });
+            }
+        }
+    }
+
+    if !found {
+        return Ok(None);
+    }
+    Ok(Some(Manifest {
+        title: title.ok_or(ManifestError::MissingTitle)?,
+        keyword,
+        description,
+        arguments,
+        output: output.unwrap_or_default(),
+        timeout: timeout.unwrap_or(Manifest::DEFAULT_TIMEOUT),
+    }))
+}
+
+// the words after a script's keyword, quotes keep spaces in one argument
+pub fn parse_arguments(query: &str) -> Vec<String> {
+    Parser::new()
+        .parse(query)
+        .terms
+        .into_iter()
+        .map(|term| match term {
+            Term::Word(text) | Term::Phrase(text) => text.text,
+            // without keys there are no filters, and `-x` is an argument like any other
+            term => query[term.span()].to_string(),
+        })
+        .collect()
+}
+
+// other fields are ignored, so scripts can share output with other launchers
+#[derive(Deserialize)]
+struct Row {
+    title: String,
+    #[serde(default)]
+    subtitle: String,
+    // acting on a row opens the first of these it has, or copies its text or else its title
+    url: Option<String>,
+    path: Option<PathBuf>,
+    text: Option<String>,
+}
+
+// `[{"title": "…", "subtitle": "…", "url": "…"}, …]`, with `path` or `text` instead of `url`
+pub fn parse_rows(stdout: &str) -> Result<Vec<SearchItem>, ScriptError> {
+    let rows: Vec<Row> =
+        serde_json::from_str(stdout).map_err(|err| ScriptError::Rows(err.to_string()))?;
+    rows.into_iter()
+        .map(|row| {
+            let target = match (row.url, row.path, row.text) {
+                (Some(url), _, _) => Target::Url(
+                    Url::parse(&url)
+                        .map_err(|err| ScriptError::Rows(format!("invalid url {url}: {err}")))?,
+                ),
+                (None, Some(path), _) => Target::Path(expand_home(&path)),
+                (None, None, Some(text)) => Target::Text(text),
+                (None, None, None) => Target::Text(row.title.clone()),
+            };
+            Ok(SearchItem {
+                name: row.title,
+                detail: row.subtitle,
+                keyword: None,
+                target,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(PathBuf, io::Error),
+    Manifest(PathBuf, ManifestError),
+    NotExecutable(PathBuf),
+    DuplicateKeyword(PathBuf, String),
+    // json output that isn't a list of rows
+    Rows(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            ScriptError::Manifest(path, err) => write!(f, "{}: {err}", path.display()),
+            ScriptError::NotExecutable(path) => {
+                write!(f, "{} has a header but isn't executable", path.display())
+            }
+            ScriptError::DuplicateKeyword(path, keyword) => write!(
+                f,
+                "{}: keyword `{keyword}` is taken by another script",
+                path.display()
+            ),
+            ScriptError::Rows(message) => write!(f, "invalid json rows: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Script {
+    // run directly, the shebang picks the interpreter
+    pub fn command(&self, args: &[String]) -> Command {
+        let mut command = Command::new(&self.path);
+        command.args(args);
+        if let Some(dir) = self.path.parent() {
+            command.current_dir(dir);
+        }
+        command
+    }
+}
+
+// every script in a directory, the files are read again whenever it changes
+#[derive(Clone)]
+pub struct Scripts {
+    dir: Arc<PathBuf>,
+    scripts: Arc<Mutex<Vec<Script>>>,
+}
+
+impl Global for Scripts {}
+
+impl Scripts {
+    pub fn default_dir() -> PathBuf {
+        Config::path().with_file_name("scripts")
+    }
+
+    // never fails, broken scripts are reported and left out until they're fixed
+    pub fn load(dir: PathBuf) -> Self {
+        let scripts = Self {
+            dir: Arc::new(dir),
+            scripts: Arc::new(Mutex::new(Vec::new())),
+        };
+        for err in scripts.reload() {
+            eprintln!("{err}");
+        }
+        scripts
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // the scripts that loaded, and errors for the ones that didn't
+    pub fn reload(&self) -> Vec<ScriptError> {
+        let mut errors = Vec::new();
+        let mut paths: Vec<PathBuf> = match fs::read_dir(self.dir.as_ref()) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                })
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                errors.push(ScriptError::Io(self.dir.to_path_buf(), err));
+                Vec::new()
+            }
+        };
+        paths.sort();
+
+        let mut scripts = Vec::new();
+        let mut keywords = HashSet::new();
+        for path in paths {
+            match Self::read(&path) {
+                Ok(Some(script)) => {
+                    if let Some(keyword) = &script.manifest.keyword
+                        && !keywords.insert(keyword.clone())
+                    {
+                        errors.push(ScriptError::DuplicateKeyword(path, keyword.clone()));
+                        continue;
+                    }
+                    scripts.push(script);
+                }
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+        *self.scripts.lock().unwrap() = scripts;
+        errors
+    }
+
+    fn read(path: &Path) -> Result<Option<Script>, ScriptError> {
+        let io_err = |err| ScriptError::Io(path.to_path_buf(), err);
+        let metadata = fs::metadata(path).map_err(io_err)?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        let mut header = Vec::new();
+        fs::File::open(path)
+            .and_then(|file| file.take(MAX_HEADER_BYTES).read_to_end(&mut header))
+            .map_err(io_err)?;
+
+        let manifest = parse_manifest(&String::from_utf8_lossy(&header))
+            .map_err(|err| ScriptError::Manifest(path.to_path_buf(), err))?;
+        let Some(manifest) = manifest else {
+            return Ok(None);
+        };
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(ScriptError::NotExecutable(path.to_path_buf()));
+        }
+        Ok(Some(Script {
+            path: path.to_path_buf(),
+            manifest,
+        }))
+    }
+
+    pub fn scripts(&self) -> Vec<Script> {
+        self.scripts.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Script> {
+        self.scripts
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|script| script.path == path)
+            .cloned()
+    }
+
+    pub fn provider(&self) -> ScriptProvider {
+        ScriptProvider {
+            scripts: self.clone(),
+        }
+    }
+
+    // the directory is created so scripts dropped in later are noticed
+    pub fn watch(&self) -> notify::Result<(RecommendedWatcher, UnboundedReceiver<()>)> {
+        let (tx, rx) = mpsc::unbounded();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if res.is_ok_and(|event| !event.kind.is_access()) {
+                let _ = tx.unbounded_send(());
+            }
+        })?;
+        fs::create_dir_all(self.dir.as_ref())?;
+        watcher.watch(&self.dir, RecursiveMode::NonRecursive)?;
+        Ok((watcher, rx))
+    }
+}
+
+// lists the scripts by title, acting on one asks for any arguments it needs
+pub struct ScriptProvider {
+    scripts: Scripts,
+}
+
+impl SearchProvider for ScriptProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let items = self
+            .scripts
+            .scripts()
+            .into_iter()
+            .map(|script| SearchItem {
+                name: script.manifest.title.clone(),
+                detail: match (&script.manifest.keyword, &script.manifest.description) {
+                    (Some(keyword), Some(description)) => {
+                        format!("Script · {keyword} · {description}")
+                    }
+                    (Some(keyword), None) => format!("Script · {keyword}"),
+                    (None, Some(description)) => format!("Script · {description}"),
+                    (None, None) => "Script".to_string(),
+                },
+                keyword: script.manifest.keyword.clone(),
+                target: Target::Script {
+                    path: script.path,
+                    args: Vec::new(),
+                },
+            })
+            .collect();
+        MemoryProvider::new(items).search(query)
+    }
+}
+
+// one row running a script with what followed its keyword
+pub struct ScriptRunProvider {
+    script: Script,
+}
+
+impl ScriptRunProvider {
+    pub fn new(script: Script) -> Self {
+        Self { script }
+    }
+}
+
+impl SearchProvider for ScriptRunProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let args = parse_arguments(query);
+        let manifest = &self.script.manifest;
+        let names: Vec<&str> = manifest
+            .arguments
+            .iter()
+            .map(|argument| argument.name.as_str())
+            .collect();
+        let detail = if args.is_empty() {
+            match names.is_empty() {
+                true => "Run script".to_string(),
+                false => format!("Run script · {}", names.join(", ")),
+            }
+        } else {
+            format!("Run script with {}", args.join(", "))
+        };
+        let item = SearchItem {
+            name: manifest.title.clone(),
+            detail,
+            keyword: None,
+            target: Target::Script {
+                path: self.script.path.clone(),
+                args,
+            },
+        };
+        MemoryProvider::new(vec![item]).search("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use futures::executor::block_on_stream;
+
+    use super::*;
+    use crate::{
+        search::SearchStatus,
+        shell::{self, Exit},
+    };
+
+    fn invalid(text: &str) -> (usize, String) {
+        match parse_manifest(text) {
+            Err(ManifestError::Invalid { line, key, .. }) => (line, key),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    fn write(dir: &Path, name: &str, body: &str, mode: u32) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, body).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    fn items(provider: &dyn SearchProvider, query: &str) -> Vec<SearchItem> {
+        let mut items = Vec::new();
+        assert_eq!(provider.search(query).poll(&mut items), SearchStatus::Done);
+        items
+    }
+
+    // the stdout lines and how it exited
+    fn run(script: &Script, args: &[&str]) -> (Vec<String>, Exit) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (_run, events) =
+            shell::spawn(script.command(&args), Some(script.manifest.timeout)).unwrap();
+        let mut stdout = Vec::new();
+        for event in block_on_stream(events) {
+            match event {
+                shell::Event::Stdout(line) => stdout.push(line),
+                shell::Event::Stderr(_) => {}
+                shell::Event::Exited(exit) => return (stdout, exit),
+            }
+        }
+        panic!("no exit");
+    }
+
+    #[test]
+    fn manifests() {
+        let manifest = parse_manifest(
+            "#!/bin/sh\n\
+             # @halcyon.title Open ticket\n\
+             #@halcyon.keyword jira\n\
+             #   @halcyon.description  Opens a ticket by its id \n\
+             # @halcyon.argument id\n\
+             # @halcyon.argument project optional\n\
+             # @halcyon.output json\n\
+             # @halcyon.timeout 10s\n\
+             echo \"@halcyon.title not a header line\"\n",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            manifest,
+            Manifest {
+                title: "Open ticket".to_string(),
+                keyword: Some("jira".to_string()),
+                description: Some("Opens a ticket by its id".to_string()),
+                arguments: vec![
+                    Argument {
+                        name: "id".to_string(),
+                        optional: false,
+                    },
+                    Argument {
+                        name: "project".to_string(),
+                        optional: true,
+                    },
+                ],
+                output: OutputMode::Rows,
+                timeout: Duration::from_secs(10),
+            }
+        );
+        assert_eq!(manifest.required(), 1);
+
+        for marker in COMMENT_MARKERS {
+            let manifest = parse_manifest(&format!("{marker} @halcyon.title X"))
+                .unwrap()
+                .unwrap();
+            assert_eq!(manifest.output, OutputMode::Inline);
+            assert_eq!(manifest.timeout, Manifest::DEFAULT_TIMEOUT);
+        }
+        let copy =
+            parse_manifest("# @halcyon.title X\n# @halcyon.output copy\n# @halcyon.timeout 45");
+        let copy = copy.unwrap().unwrap();
+        assert_eq!(copy.output, OutputMode::Copy);
+        assert_eq!(copy.timeout, Duration::from_secs(45));
+
+        // helpers without a header aren't scripts
+        assert_eq!(parse_manifest("#!/bin/sh\necho hi\n"), Ok(None));
+        assert_eq!(parse_manifest(""), Ok(None));
+    }
+
+    #[test]
+    fn header_ends_at_the_first_line_of_code() {
+        let manifest = parse_manifest(
+            "#!/usr/bin/env python3\n\
+             # a script of mine\n\
+             \n\
+             # @halcyon.title X\n\
+             import sys\n\
+             # @halcyon.title Y\n\
+             # @halcyon.bogus\n",
+        );
+        assert_eq!(manifest.unwrap().unwrap().title, "X");
+        assert_eq!(
+            parse_manifest("#!/bin/sh\necho hi\n# @halcyon.title X\n"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn manifest_errors() {
+        assert_eq!(
+            parse_manifest("-- @halcyon.keyword x"),
+            Err(ManifestError::MissingTitle)
+        );
+        assert_eq!(
+            parse_manifest("# @halcyon.title a\n# @halcyon.foo b"),
+            Err(ManifestError::UnknownKey {
+                line: 2,
+                key: "foo".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_manifest("# @halcyon.title a\n\n# @halcyon.title b"),
+            Err(ManifestError::Duplicate {
+                line: 3,
+                key: "title".to_string(),
+            })
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.argument a optional\n# @halcyon.argument b"),
+            (3, "argument".to_string())
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.argument a sometimes"),
+            (2, "argument".to_string())
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.timeout 0"),
+            (2, "timeout".to_string())
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.timeout x"),
+            (2, "timeout".to_string())
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.output xml"),
+            (2, "output".to_string())
+        );
+        assert_eq!(
+            invalid("# @halcyon.title a\n# @halcyon.keyword a b"),
+            (2, "keyword".to_string())
+        );
+        assert_eq!(invalid("# @halcyon.title"), (1, "title".to_string()));
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(
+            parse_arguments(r#"abc "two words" -x a\ b kind:pdf"#),
+            ["abc", "two words", "-x", "a b", "kind:pdf"]
+        );
+        assert_eq!(parse_arguments(r#"-"a b" ""#), ["-\"a b\"", ""]);
+        assert!(parse_arguments("   ").is_empty());
+    }
+
+    #[test]
+    fn rows() {
+        let rows = parse_rows(
+            r#"[
+                {"title": "a", "subtitle": "s", "url": "https://x.com", "text": "ignored"},
+                {"title": "b", "path": "/tmp", "text": "ignored"},
+                {"title": "c", "text": "t"},
+                {"title": "d", "link": "x"}
+            ]"#,
+        )
+        .unwrap();
+        let rows: Vec<(&str, &str, &Target)> = rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.detail.as_str(), &row.target))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("a", "s", &Target::Url(Url::parse("https://x.com").unwrap())),
+                ("b", "", &Target::Path("/tmp".into())),
+                ("c", "", &Target::Text("t".to_string())),
+                ("d", "", &Target::Text("d".to_string())),
+            ]
+        );
+        assert!(parse_rows("[]").unwrap().is_empty());
+
+        for stdout in ["", "{}", "[{}]", r#"[{"title": "a", "url": "nope"}]"#] {
+            assert!(
+                matches!(parse_rows(stdout), Err(ScriptError::Rows(_))),
+                "{stdout}"
+            );
+        }
+    }
+
+    #[test]
+    fn loads_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let echo = write(
+            dir,
+            "echo.sh",
+            "#!/bin/sh\n# @halcyon.title Echo\n# @halcyon.keyword ec\necho \"$@\"\n",
+            0o755,
+        );
+        write(
+            dir,
+            "taken.sh",
+            "#!/bin/sh\n# @halcyon.title Taken\n# @halcyon.keyword ec\n",
+            0o755,
+        );
+        write(
+            dir,
+            "plain.sh",
+            "#!/bin/sh\n# @halcyon.title Plain\n",
+            0o644,
+        );
+        write(dir, "helper.sh", "#!/bin/sh\necho\n", 0o644);
+        write(dir, ".hidden", "# @halcyon.title Hidden\n", 0o755);
+        write(
+            dir,
+            "bad.py",
+            "#!/usr/bin/env python3\n# @halcyon.nope x\n",
+            0o755,
+        );
+        fs::create_dir(dir.join("folder")).unwrap();
+
+        let scripts = Scripts::load(dir.to_path_buf());
+        let titles: Vec<String> = scripts
+            .scripts()
+            .into_iter()
+            .map(|script| script.manifest.title)
+            .collect();
+        assert_eq!(titles, ["Echo"]);
+        assert_eq!(scripts.get(&echo).unwrap().path, echo);
+
+        let errors = scripts.reload();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(&errors[0], ScriptError::Manifest(path, _) if path.ends_with("bad.py")));
+        assert!(
+            matches!(&errors[1], ScriptError::NotExecutable(path) if path.ends_with("plain.sh"))
+        );
+        assert!(matches!(
+            &errors[2],
+            ScriptError::DuplicateKeyword(path, keyword) if path.ends_with("taken.sh") && keyword == "ec"
+        ));
+
+        // fixed scripts come back on reload
+        fs::set_permissions(dir.join("plain.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(scripts.reload().len(), 2);
+        assert_eq!(scripts.scripts().len(), 2);
+
+        let missing = Scripts::load(dir.join("missing"));
+        assert!(missing.scripts().is_empty());
+        assert!(missing.reload().is_empty());
+    }
+
+    #[test]
+    fn providers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "ticket",
+            "#!/bin/sh\n# @halcyon.title Open ticket\n# @halcyon.keyword jira\n\
+             # @halcyon.description By id\n# @halcyon.argument id\n# @halcyon.argument project optional\n",
+            0o755,
+        );
+        let scripts = Scripts::load(dir.path().to_path_buf());
+
+        let found = items(&scripts.provider(), "ticket");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].detail, "Script · jira · By id");
+        assert_eq!(found[0].keyword.as_deref(), Some("jira"));
+        assert_eq!(
+            found[0].target,
+            Target::Script {
+                path: path.clone(),
+                args: Vec::new(),
+            }
+        );
+
+        let run = ScriptRunProvider::new(scripts.get(&path).unwrap());
+        assert_eq!(items(&run, "")[0].detail, "Run script · id, project");
+        let found = items(&run, r#"ABC-1 "big project""#);
+        assert_eq!(found[0].detail, "Run script with ABC-1, big project");
+        assert_eq!(
+            found[0].target,
+            Target::Script {
+                path,
+                args: vec!["ABC-1".to_string(), "big project".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn runs_sample_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "echo.sh",
+            "#!/bin/sh\n# @halcyon.title Echo\n# @halcyon.output copy\n\
+             printf '%s\\n' \"$@\"\necho warning >&2\nbasename \"$PWD\"\n",
+            0o755,
+        );
+        write(
+            dir.path(),
+            "rows.sh",
+            "#!/bin/sh\n# @halcyon.title Rows\n# @halcyon.output json\n\
+             echo '[{\"title\": \"one\", \"url\": \"https://example.com\"},'\n\
+             echo ' {\"title\": \"two\", \"text\": \"copied\"}]'\n",
+            0o755,
+        );
+        write(
+            dir.path(),
+            "fail.sh",
+            "#!/bin/sh\n# @halcyon.title Fail\necho partial\nexit 4\n",
+            0o755,
+        );
+        let scripts = Scripts::load(dir.path().to_path_buf());
+        let script = |name: &str| scripts.get(&dir.path().join(name)).unwrap();
+
+        // arguments aren't split again and run in the script's directory
+        let name = dir
+            .path()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(
+            run(&script("echo.sh"), &["one", "two words", "$HOME"]),
+            (
+                vec![
+                    "one".to_string(),
+                    "two words".to_string(),
+                    "$HOME".to_string(),
+                    name
+                ],
+                Exit::Code(0)
+            )
+        );
+
+        let (stdout, exit) = run(&script("rows.sh"), &[]);
+        assert_eq!(exit, Exit::Code(0));
+        let rows = parse_rows(&stdout.join("\n")).unwrap();
+        assert_eq!(rows[1].target, Target::Text("copied".to_string()));
+
+        assert_eq!(
+            run(&script("fail.sh"), &[]),
+            (vec!["partial".to_string()], Exit::Code(4))
+        );
+    }
+
+    #[test]
+    fn slow_scripts_time_out() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "slow",
+            "#!/bin/sh\n# @halcyon.title Slow\n# @halcyon.timeout 1\necho start\nsleep 30\n",
+            0o755,
+        );
+        let scripts = Scripts::load(dir.path().to_path_buf());
+        let script = scripts.scripts().remove(0);
+        assert_eq!(script.manifest.timeout, Duration::from_secs(1));
+
+        let started = Instant::now();
+        assert_eq!(
+            run(&script, &[]),
+            (vec!["start".to_string()], Exit::TimedOut)
+        );
+        assert!(started.elapsed() < Duration::from_secs(1) + shell::KILL_AFTER);
+    }
+}
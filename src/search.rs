@@ -0,0 +1,677 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use chrono::Utc;
+use url::Url;
+
+use crate::{
+    fuzzy::{self, ScoredItem},
+    history::History,
+    query::Parser,
+};
+
+pub struct SearchQuery {
+    pub query: String,
+    // bumped by the view for every query, echoed back so stale responses can be dropped
+    pub generation: u64,
+    pub response_tx: oneshot::Sender<SearchResponse>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchItem {
+    pub name: String,
+    // shown under the name
+    pub detail: String,
+    // typing it exactly ranks the item first, e.g. a snippet's abbreviation
+    pub keyword: Option<String>,
+    pub target: Target,
+}
+
+// what acting on an item acts on
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Target {
+    Path(PathBuf),
+    // a clipboard history entry, by id
+    Clip(u64),
+    // a snippet, by name
+    Snippet(String),
+    Url(Url),
+    // a shell command to run
+    Command(String),
+    // a script from the scripts directory, with the arguments typed after its keyword
+    Script { path: PathBuf, args: Vec<String> },
+    // copied as is, e.g. a row a script printed
+    Text(String),
+}
+
+impl SearchItem {
+    pub fn file(name: impl Into<String>, path: PathBuf) -> Self {
+        Self {
+            name: name.into(),
+            detail: path.to_string_lossy().to_string(),
+            keyword: None,
+            target: Target::Path(path),
+        }
+    }
+}
+
+// a value worked out from the query itself rather than found, e.g. the result of arithmetic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Answer {
+    // what gets copied
+    pub value: String,
+    pub detail: String,
+}
+
+// orders what the providers found for a query, dropping what doesn't match, with `boost` added
+// to the score of each item that does
+pub type Ranker = fn(&str, Vec<SearchItem>, &dyn Fn(&SearchItem) -> i64) -> Vec<ScoredItem>;
+
+// keeps the providers' order, for items that aren't found by matching the query, like a web
+// search for it
+pub fn unranked(
+    _query: &str,
+    items: Vec<SearchItem>,
+    _boost: &dyn Fn(&SearchItem) -> i64,
+) -> Vec<ScoredItem> {
+    items
+        .into_iter()
+        .map(|item| ScoredItem {
+            item,
+            score: 0,
+            positions: Vec::new(),
+        })
+        .collect()
+}
+
+pub struct SearchResponse {
+    pub generation: u64,
+    // shown above the results
+    pub answers: Vec<Answer>,
+    pub results: Vec<ScoredItem>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchStatus {
+    Gathering,
+    Done,
+}
+
+pub trait SearchProvider: Send {
+    fn search(&self, query: &str) -> Box<dyn Search>;
+}
+
+// answers are cheap to compute, so unlike providers they're asked synchronously
+pub trait Answerer: Send {
+    fn answer(&self, query: &str) -> Vec<Answer>;
+}
+
+// a single in-flight search, polled until it reports `Done` or is cancelled
+pub trait Search {
+    // pushes any items found since the last poll
+    fn poll(&mut self, items: &mut Vec<SearchItem>) -> SearchStatus;
+    fn cancel(&mut self);
+}
+
+// serves a fixed set of items, mostly useful for tests and off macOS
+pub struct MemoryProvider {
+    items: Vec<SearchItem>,
+}
+
+impl MemoryProvider {
+    pub fn new(items: Vec<SearchItem>) -> Self {
+        Self { items }
+    }
+}
+
+impl SearchProvider for MemoryProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let items = self
+            .items
+            .iter()
+            .filter(|item| fuzzy::match_item(query, item).is_some())
+            .cloned()
+            .collect();
+        Box::new(MemorySearch { items })
+    }
+}
+
+struct MemorySearch {
+    items: Vec<SearchItem>,
+}
+
+impl Search for MemorySearch {
+    fn poll(&mut self, items: &mut Vec<SearchItem>) -> SearchStatus {
+        items.append(&mut self.items);
+        SearchStatus::Done
+    }
+
+    fn cancel(&mut self) {
+        self.items.clear();
+    }
+}
+
+// providers and answerers that take over queries starting with one of `prefixes`, which
+// they're given without it, e.g. `'` for files or `=` for the calculator
+pub struct Route {
+    parser: Parser,
+    providers: Vec<Box<dyn SearchProvider>>,
+    answerers: Vec<Box<dyn Answerer>>,
+    ranker: Ranker,
+}
+
+impl Route {
+    pub fn new(prefixes: Vec<String>, providers: Vec<Box<dyn SearchProvider>>) -> Self {
+        Self {
+            parser: Parser::new().with_triggers(prefixes),
+            providers,
+            answerers: Vec::new(),
+            ranker: fuzzy::rank_by,
+        }
+    }
+
+    pub fn with_answerer(mut self, answerer: impl Answerer + 'static) -> Self {
+        self.answerers.push(Box::new(answerer));
+        self
+    }
+
+    pub fn with_ranker(mut self, ranker: Ranker) -> Self {
+        self.ranker = ranker;
+        self
+    }
+}
+
+struct Pending {
+    query: String,
+    ranker: Ranker,
+    // whether the fallbacks get asked if nothing matches
+    fall_back: bool,
+    generation: u64,
+    searches: Vec<Box<dyn Search>>,
+    answers: Vec<Answer>,
+    items: Vec<SearchItem>,
+    tx: oneshot::Sender<SearchResponse>,
+}
+
+impl Pending {
+    fn cancel(&mut self) {
+        for search in self.searches.iter_mut() {
+            search.cancel();
+        }
+    }
+}
+
+// runs every provider for each incoming query and answers with the ranked results once they
+// have all finished
+pub struct Searcher {
+    providers: Vec<Box<dyn SearchProvider>>,
+    answerers: Vec<Box<dyn Answerer>>,
+    ranker: Ranker,
+    routes: Vec<Route>,
+    fallbacks: Vec<Box<dyn SearchProvider>>,
+    history: Option<History>,
+    debounce: Duration,
+    poll_interval: Duration,
+}
+
+impl Searcher {
+    pub const DEBOUNCE: Duration = Duration::from_millis(40);
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    pub fn new(providers: Vec<Box<dyn SearchProvider>>) -> Self {
+        Self {
+            providers,
+            answerers: Vec::new(),
+            ranker: fuzzy::rank_by,
+            routes: Vec::new(),
+            fallbacks: Vec::new(),
+            history: None,
+            debounce: Self::DEBOUNCE,
+            poll_interval: Self::POLL_INTERVAL,
+        }
+    }
+
+    pub fn with_answerer(mut self, answerer: impl Answerer + 'static) -> Self {
+        self.answerers.push(Box::new(answerer));
+        self
+    }
+
+    pub fn with_ranker(mut self, ranker: Ranker) -> Self {
+        self.ranker = ranker;
+        self
+    }
+
+    pub fn with_route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    // asked when nothing else matches a query, e.g. a web search for it
+    pub fn with_fallback(mut self, provider: impl SearchProvider + 'static) -> Self {
+        self.fallbacks.push(Box::new(provider));
+        self
+    }
+
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // blocks until the view drops its sender
+    pub fn run(self, query_rx: Receiver<SearchQuery>) {
+        let mut cur: Option<Pending> = None;
+
+        loop {
+            // sleep on the channel when idle, otherwise wake up to poll the providers
+            let next = match cur {
+                None => match query_rx.recv() {
+                    Ok(query) => Some(query),
+                    Err(_) => return,
+                },
+                Some(_) => match query_rx.recv_timeout(self.poll_interval) {
+                    Ok(query) => Some(query),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+            };
+
+            if let Some(query) = next {
+                let Some(query) = self.debounce(query, &query_rx) else {
+                    return;
+                };
+                // dropping the superseded sender tells its receiver no answer is coming
+                if let Some(mut pending) = cur.take() {
+                    pending.cancel();
+                }
+                cur = Some(self.start(query));
+            }
+
+            if let Some(pending) = cur.take() {
+                cur = self.poll(pending);
+            }
+        }
+    }
+
+    // keeps swapping in newer queries until the user stops typing for a moment
+    fn debounce(
+        &self,
+        mut query: SearchQuery,
+        query_rx: &Receiver<SearchQuery>,
+    ) -> Option<SearchQuery> {
+        loop {
+            match query_rx.recv_timeout(self.debounce) {
+                Ok(newer) => query = newer,
+                Err(RecvTimeoutError::Timeout) => return Some(query),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn start(&self, query: SearchQuery) -> Pending {
+        let SearchQuery {
+            query,
+            generation,
+            response_tx,
+        } = query;
+
+        let route = self.routes.iter().find_map(|route| {
+            let trigger = route.parser.trigger(&query)?;
+            Some((route, query[trigger.span.end..].trim_start().to_string()))
+        });
+        if let Some((route, query)) = route {
+            return Pending {
+                searches: route.providers.iter().map(|p| p.search(&query)).collect(),
+                answers: route
+                    .answerers
+                    .iter()
+                    .flat_map(|a| a.answer(&query))
+                    .collect(),
+                query,
+                ranker: route.ranker,
+                fall_back: false,
+                generation,
+                items: Vec::new(),
+                tx: response_tx,
+            };
+        }
+
+        Pending {
+            searches: self.providers.iter().map(|p| p.search(&query)).collect(),
+            answers: self
+                .answerers
+                .iter()
+                .flat_map(|a| a.answer(&query))
+                .collect(),
+            fall_back: !self.fallbacks.is_empty() && !query.trim().is_empty(),
+            query,
+            ranker: self.ranker,
+            generation,
+            items: Vec::new(),
+            tx: response_tx,
+        }
+    }
+
+    // answers and returns `None` once every provider is done
+    fn poll(&self, mut pending: Pending) -> Option<Pending> {
+        pending
+            .searches
+            .retain_mut(|search| search.poll(&mut pending.items) == SearchStatus::Gathering);
+        if !pending.searches.is_empty() {
+            return Some(pending);
+        }
+
+        // providers can overlap, keep the first hit for each target
+        let mut seen = HashSet::new();
+        let mut items = std::mem::take(&mut pending.items);
+        items.retain(|item| seen.insert(item.target.clone()));
+        let query = &pending.query;
+        let now = Utc::now();
+        let boost = |item: &SearchItem| match (&self.history, &item.target) {
+            (Some(history), Target::Path(path)) => history.boost(path, query, now),
+            _ => 0,
+        };
+        // there's nothing to rank an empty query by, keep the providers' order
+        let ranker = if query.trim().is_empty() {
+            unranked
+        } else {
+            pending.ranker
+        };
+        let results = ranker(query, items, &boost);
+
+        if results.is_empty() && pending.answers.is_empty() && pending.fall_back {
+            pending.searches = self.fallbacks.iter().map(|p| p.search(query)).collect();
+            pending.ranker = unranked;
+            pending.fall_back = false;
+            return Some(pending);
+        }
+
+        // the view may have moved on to a newer query already
+        let _ = pending.tx.send(SearchResponse {
+            generation: pending.generation,
+            answers: pending.answers,
+            results,
+        });
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex, mpsc},
+        thread,
+    };
+
+    use super::*;
+
+    fn app(name: &str) -> SearchItem {
+        SearchItem::file(name, PathBuf::from(format!("/Applications/{name}.app")))
+    }
+
+    fn names(items: &[SearchItem]) -> Vec<&str> {
+        items.iter().map(|item| item.name.as_str()).collect()
+    }
+
+    #[test]
+    fn memory_provider_polls_until_done() {
+        let provider = MemoryProvider::new(vec![app("Safari"), app("Calculator")]);
+        let mut search = provider.search("safari");
+        let mut items = Vec::new();
+        assert_eq!(search.poll(&mut items), SearchStatus::Done);
+        assert_eq!(names(&items), ["Safari"]);
+        // nothing is served twice
+        assert_eq!(search.poll(&mut items), SearchStatus::Done);
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn memory_provider_lists_everything_for_an_empty_query() {
+        let provider = MemoryProvider::new(vec![app("Safari"), app("Slack")]);
+        let mut items = Vec::new();
+        provider.search("").poll(&mut items);
+        assert_eq!(names(&items), ["Safari", "Slack"]);
+    }
+
+    #[test]
+    fn cancelled_memory_search_serves_nothing() {
+        let provider = MemoryProvider::new(vec![app("Safari")]);
+        let mut search = provider.search("safari");
+        search.cancel();
+        let mut items = Vec::new();
+        assert_eq!(search.poll(&mut items), SearchStatus::Done);
+        assert!(items.is_empty());
+    }
+
+    // gathers for `polls` polls before serving its items, logging what it's asked to do
+    struct FakeProvider {
+        items: Vec<SearchItem>,
+        polls: usize,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeProvider {
+        fn new(items: Vec<SearchItem>, polls: usize, log: &Arc<Mutex<Vec<String>>>) -> Self {
+            Self {
+                items,
+                polls,
+                log: log.clone(),
+            }
+        }
+    }
+
+    impl SearchProvider for FakeProvider {
+        fn search(&self, query: &str) -> Box<dyn Search> {
+            self.log.lock().unwrap().push(format!("search {query}"));
+            Box::new(FakeSearch {
+                query: query.to_string(),
+                items: self.items.clone(),
+                polls: self.polls,
+                log: self.log.clone(),
+            })
+        }
+    }
+
+    struct FakeSearch {
+        query: String,
+        items: Vec<SearchItem>,
+        polls: usize,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Search for FakeSearch {
+        fn poll(&mut self, items: &mut Vec<SearchItem>) -> SearchStatus {
+            if self.polls > 0 {
+                self.polls -= 1;
+                return SearchStatus::Gathering;
+            }
+            items.append(&mut self.items);
+            SearchStatus::Done
+        }
+
+        fn cancel(&mut self) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("cancel {}", self.query));
+        }
+    }
+
+    fn start(searcher: Searcher) -> mpsc::Sender<SearchQuery> {
+        let (tx, rx) = mpsc::channel();
+        let searcher = searcher
+            .with_debounce(Duration::ZERO)
+            .with_poll_interval(Duration::from_millis(1));
+        thread::spawn(move || searcher.run(rx));
+        tx
+    }
+
+    fn send(
+        tx: &mpsc::Sender<SearchQuery>,
+        query: &str,
+        generation: u64,
+    ) -> oneshot::Receiver<SearchResponse> {
+        let (response_tx, response_rx) = oneshot::channel();
+        tx.send(SearchQuery {
+            query: query.to_string(),
+            generation,
+            response_tx,
+        })
+        .unwrap();
+        response_rx
+    }
+
+    fn result_names(response: &SearchResponse) -> Vec<&str> {
+        response
+            .results
+            .iter()
+            .map(|result| result.item.name.as_str())
+            .collect()
+    }
+
+    fn wait_for(log: &Arc<Mutex<Vec<String>>>, entry: &str) {
+        for _ in 0..1000 {
+            if log.lock().unwrap().iter().any(|logged| logged == entry) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("never logged {entry}: {:?}", log.lock().unwrap());
+    }
+
+    #[test]
+    fn waits_for_slow_providers() {
+        let log = Arc::default();
+        let tx = start(Searcher::new(vec![
+            Box::new(FakeProvider::new(vec![app("Safari")], 0, &log)),
+            Box::new(FakeProvider::new(vec![app("Safari Preview")], 20, &log)),
+        ]));
+        let response = send(&tx, "safari", 1).recv().unwrap();
+        assert_eq!(response.generation, 1);
+        assert_eq!(result_names(&response), ["Safari", "Safari Preview"]);
+    }
+
+    #[test]
+    fn drops_duplicate_targets() {
+        let log = Arc::default();
+        let tx = start(Searcher::new(vec![
+            Box::new(FakeProvider::new(vec![app("Safari")], 0, &log)),
+            Box::new(FakeProvider::new(vec![app("Safari")], 3, &log)),
+        ]));
+        let response = send(&tx, "safari", 1).recv().unwrap();
+        assert_eq!(result_names(&response), ["Safari"]);
+    }
+
+    #[test]
+    fn debounced_queries_answer_only_the_latest() {
+        let log = Arc::default();
+        let (tx, rx) = mpsc::channel();
+        let searcher = Searcher::new(vec![Box::new(FakeProvider::new(
+            vec![app("Safari"), app("Calculator")],
+            0,
+            &log,
+        ))])
+        .with_debounce(Duration::from_millis(50));
+        let first = send(&tx, "saf", 1);
+        let second = send(&tx, "calc", 2);
+        thread::spawn(move || searcher.run(rx));
+
+        // the superseded query's sender is dropped without an answer
+        assert!(first.recv().is_err());
+        let response = second.recv().unwrap();
+        assert_eq!(response.generation, 2);
+        assert_eq!(result_names(&response), ["Calculator"]);
+        assert_eq!(*log.lock().unwrap(), ["search calc"]);
+    }
+
+    #[test]
+    fn cancels_a_superseded_query() {
+        let log = Arc::default();
+        let tx = start(Searcher::new(vec![Box::new(FakeProvider::new(
+            vec![app("Safari")],
+            usize::MAX,
+            &log,
+        ))]));
+        let first = send(&tx, "saf", 1);
+        wait_for(&log, "search saf");
+        let second = send(&tx, "safari", 2);
+        wait_for(&log, "cancel saf");
+        assert!(first.recv().is_err());
+        // the second search never finishes either, but it was started
+        wait_for(&log, "search safari");
+        drop(tx);
+        assert!(second.recv().is_err());
+    }
+
+    #[test]
+    fn generations_are_echoed_even_out_of_order() {
+        let log = Arc::default();
+        let tx = start(Searcher::new(vec![Box::new(FakeProvider::new(
+            vec![app("Safari")],
+            0,
+            &log,
+        ))]));
+        // it's up to the view to drop a response for a generation it has moved past
+        assert_eq!(send(&tx, "safari", 7).recv().unwrap().generation, 7);
+        assert_eq!(send(&tx, "safari", 3).recv().unwrap().generation, 3);
+    }
+
+    #[test]
+    fn falls_back_when_nothing_matches() {
+        let log = Arc::default();
+        let searcher = Searcher::new(vec![Box::new(FakeProvider::new(
+            vec![app("Safari")],
+            0,
+            &log,
+        ))])
+        .with_fallback(FakeProvider::new(vec![app("Search the web")], 2, &log));
+        let tx = start(searcher);
+
+        let response = send(&tx, "safari", 1).recv().unwrap();
+        assert_eq!(result_names(&response), ["Safari"]);
+        let response = send(&tx, "zzz", 2).recv().unwrap();
+        // fallbacks aren't ranked, they're whatever the query was
+        assert_eq!(result_names(&response), ["Search the web"]);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["search safari", "search zzz", "search zzz"]
+        );
+    }
+
+    #[test]
+    fn routes_take_over_their_prefix() {
+        let log = Arc::default();
+        let searcher = Searcher::new(vec![Box::new(FakeProvider::new(
+            vec![app("Safari")],
+            0,
+            &log,
+        ))])
+        .with_route(Route::new(
+            vec!["'".to_string()],
+            vec![Box::new(FakeProvider::new(
+                vec![app("safari.pdf")],
+                0,
+                &log,
+            ))],
+        ));
+        let tx = start(searcher);
+
+        let response = send(&tx, "' safari", 1).recv().unwrap();
+        assert_eq!(result_names(&response), ["safari.pdf"]);
+        assert_eq!(*log.lock().unwrap(), ["search safari"]);
+    }
+}
@@ -0,0 +1,562 @@
+use std::{
+    fmt, fs,
+    io::{self, BufRead, BufReader, Read},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use gpui::Global;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ShellConfig,
+    fuzzy,
+    search::{MemoryProvider, Search, SearchItem, SearchProvider, Target},
+    storage::Storage,
+};
+
+// what a running command reports, `Exited` always comes last
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    // a line without its line ending
+    Stdout(String),
+    Stderr(String),
+    Exited(Exit),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    Code(i32),
+    Signal(i32),
+    Cancelled,
+    TimedOut,
+}
+
+impl Exit {
+    pub fn success(self) -> bool {
+        self == Exit::Code(0)
+    }
+}
+
+impl From<ExitStatus> for Exit {
+    fn from(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Exit::Code(code),
+            (None, Some(signal)) => Exit::Signal(signal),
+            (None, None) => Exit::Code(-1),
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exit::Code(0) => write!(f, "done"),
+            Exit::Code(code) => write!(f, "exited with {code}"),
+            Exit::Signal(signal) => write!(f, "killed by signal {signal}"),
+            Exit::Cancelled => write!(f, "cancelled"),
+            Exit::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+// runs commands like a terminal would, through a login shell so the user's PATH and profile
+// apply
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shell {
+    program: PathBuf,
+    cwd: PathBuf,
+}
+
+impl Shell {
+    pub fn new(program: impl Into<PathBuf>, cwd: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            cwd: cwd.into(),
+        }
+    }
+
+    pub fn from_config(config: &ShellConfig) -> Self {
+        let program = match &config.program {
+            program if program.as_os_str().is_empty() => Self::login(),
+            program => program.clone(),
+        };
+        Self::new(program, &config.cwd)
+    }
+
+    // $SHELL is the login shell on macOS and linux alike
+    pub fn login() -> PathBuf {
+        std::env::var_os("SHELL")
+            .filter(|shell| !shell.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/bin/sh"))
+    }
+
+    pub fn spawn(&self, command: &str) -> io::Result<(Run, UnboundedReceiver<Event>)> {
+        let mut shell = Command::new(&self.program);
+        shell
+            .arg("-l")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&self.cwd);
+        spawn(shell, None)
+    }
+}
+
+// cancelled commands get this long to exit before they're killed
+pub const KILL_AFTER: Duration = Duration::from_secs(2);
+
+// output streams in as the command writes it, dropping the `Run` cancels the command and so
+// does running past `timeout`
+pub fn spawn(
+    mut command: Command,
+    timeout: Option<Duration>,
+) -> io::Result<(Run, UnboundedReceiver<Event>)> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // a group of its own, so stopping it reaches everything the command started
+        .process_group(0)
+        .spawn()?;
+
+    let (tx, rx) = mpsc::unbounded();
+    let readers = [
+        read_lines(child.stdout.take(), Event::Stdout, tx.clone()),
+        read_lines(child.stderr.take(), Event::Stderr, tx.clone()),
+    ];
+    let pid = child.id();
+    let state = Arc::new(State::default());
+    let run = Run {
+        pid,
+        state: state.clone(),
+    };
+    if let Some(timeout) = timeout {
+        let state = state.clone();
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            if !state.finished.load(Ordering::SeqCst) && !state.cancelled.load(Ordering::SeqCst) {
+                state.timed_out.store(true, Ordering::SeqCst);
+                stop(pid, &state);
+            }
+        });
+    }
+    thread::Builder::new()
+        .name("command".into())
+        .spawn(move || {
+            // every line is sent before the exit
+            for reader in readers {
+                let _ = reader.join();
+            }
+            let exit = match child.wait() {
+                Ok(_) if state.cancelled.load(Ordering::SeqCst) => Exit::Cancelled,
+                Ok(_) if state.timed_out.load(Ordering::SeqCst) => Exit::TimedOut,
+                Ok(status) => Exit::from(status),
+                Err(err) => {
+                    eprintln!("failed to wait for command: {err}");
+                    Exit::Code(-1)
+                }
+            };
+            state.finished.store(true, Ordering::SeqCst);
+            let _ = tx.unbounded_send(Event::Exited(exit));
+        })?;
+    Ok((run, rx))
+}
+
+// asks the command's group to stop, and kills it if it hasn't after a moment
+fn stop(pid: u32, state: &Arc<State>) {
+    let group = -(pid as i32);
+    unsafe { libc::kill(group, libc::SIGTERM) };
+    let state = state.clone();
+    thread::spawn(move || {
+        thread::sleep(KILL_AFTER);
+        if !state.finished.load(Ordering::SeqCst) {
+            unsafe { libc::kill(group, libc::SIGKILL) };
+        }
+    });
+}
+
+// the lines are lossily decoded, output isn't always utf-8
+fn read_lines(
+    stream: Option<impl Read + Send + 'static>,
+    event: fn(String) -> Event,
+    tx: UnboundedSender<Event>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let Some(stream) = stream else {
+            return;
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&line);
+            // keep reading after the receiver is gone, a full pipe would block the command
+            let _ = tx.unbounded_send(event(text.trim_end_matches(['\n', '\r']).to_string()));
+        }
+    })
+}
+
+#[derive(Default)]
+struct State {
+    cancelled: AtomicBool,
+    timed_out: AtomicBool,
+    finished: AtomicBool,
+}
+
+// a handle on a running command
+pub struct Run {
+    pid: u32,
+    state: Arc<State>,
+}
+
+impl Run {
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self) {
+        if self.is_finished() || self.state.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        stop(self.pid, &self.state);
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct CommandHistoryFile {
+    version: u32,
+    // oldest first
+    commands: Vec<String>,
+}
+
+// commands run from the panel, kept apart from launches so they don't skew app ranking
+#[derive(Clone)]
+pub struct CommandHistory {
+    path: Arc<PathBuf>,
+    storage: Storage,
+    // off when the file couldn't be read, so it isn't overwritten
+    writable: bool,
+    commands: Arc<Mutex<Vec<String>>>,
+}
+
+impl Global for CommandHistory {}
+
+impl CommandHistory {
+    pub const MAX_COMMANDS: usize = 500;
+    // previous commands offered under the one being typed
+    pub const MAX_SUGGESTIONS: usize = 20;
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("halcyon")
+            .join("commands.json")
+    }
+
+    // never fails, a missing or unreadable file just means an empty history
+    pub fn load(path: PathBuf, storage: Storage) -> Self {
+        let loaded = storage.load::<CommandHistoryFile>(&path, "command history", SCHEMA_VERSION);
+        let writable = loaded.writable;
+        let commands = loaded.file.map(|file| file.commands).unwrap_or_default();
+        Self {
+            path: Arc::new(path),
+            storage,
+            writable,
+            commands: Arc::new(Mutex::new(commands)),
+        }
+    }
+
+    // newest first
+    pub fn commands(&self) -> Vec<String> {
+        self.commands
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    // running a command again moves it to the front
+    pub fn record(&self, command: &str) -> io::Result<()> {
+        {
+            let mut commands = self.commands.lock().unwrap();
+            commands.retain(|previous| previous != command);
+            commands.push(command.to_string());
+            if commands.len() > Self::MAX_COMMANDS {
+                let excess = commands.len() - Self::MAX_COMMANDS;
+                commands.drain(..excess);
+            }
+        }
+        self.save()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if !self.writable {
+            return Ok(());
+        }
+        let file = CommandHistoryFile {
+            version: SCHEMA_VERSION,
+            commands: self.commands.lock().unwrap().clone(),
+        };
+        let bytes = serde_json::to_vec(&file)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(self.storage.write(&self.path, &bytes)?)
+    }
+
+    pub fn provider(&self) -> CommandProvider {
+        CommandProvider {
+            history: self.clone(),
+        }
+    }
+}
+
+// the typed command first, then previous ones that match it, newest first
+pub struct CommandProvider {
+    history: CommandHistory,
+}
+
+impl SearchProvider for CommandProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let query = query.trim();
+        let mut items = Vec::new();
+        if !query.is_empty() {
+            items.push(SearchItem {
+                name: query.to_string(),
+                detail: "Run in shell".to_string(),
+                keyword: None,
+                target: Target::Command(query.to_string()),
+            });
+        }
+        items.extend(
+            self.history
+                .commands()
+                .into_iter()
+                .filter(|command| command != query)
+                .filter(|command| fuzzy::fuzzy_match(query, command).is_some())
+                .take(CommandHistory::MAX_SUGGESTIONS)
+                .map(|command| SearchItem {
+                    name: command.clone(),
+                    detail: "Run again".to_string(),
+                    keyword: None,
+                    target: Target::Command(command),
+                }),
+        );
+        MemoryProvider::new(items).search("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use futures::executor::block_on_stream;
+
+    use super::*;
+    use crate::storage::Secret;
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    fn run(script: &str, timeout: Option<Duration>) -> Vec<Event> {
+        let (_run, rx) = spawn(sh(script), timeout).unwrap();
+        block_on_stream(rx).collect()
+    }
+
+    fn stdout(line: &str) -> Event {
+        Event::Stdout(line.to_string())
+    }
+
+    fn stderr(line: &str) -> Event {
+        Event::Stderr(line.to_string())
+    }
+
+    // the process is gone once it can't be signalled
+    fn wait_for_exit(pid: i32) -> bool {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(5) {
+            if unsafe { libc::kill(pid, 0) } != 0 {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn streams_lines_in_order() {
+        // a last line without a newline is only complete once the command exits
+        let events = run(
+            "echo one; sleep 0.1; printf 'two \\377\\n' >&2; sleep 0.1; printf 'three\\r\\nfour'",
+            None,
+        );
+        assert_eq!(
+            events,
+            [
+                stdout("one"),
+                stderr("two \u{fffd}"),
+                stdout("three"),
+                stdout("four"),
+                Event::Exited(Exit::Code(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn exit_codes_and_signals() {
+        assert_eq!(run("exit 3", None), [Event::Exited(Exit::Code(3))]);
+        assert_eq!(
+            run("kill -9 $$", None),
+            [Event::Exited(Exit::Signal(libc::SIGKILL))]
+        );
+        // everything written before the exit is still sent
+        let events = run("yes | head -n 10000; exit 1", None);
+        assert_eq!(events.len(), 10_001);
+        assert_eq!(events.last(), Some(&Event::Exited(Exit::Code(1))));
+
+        assert!(Exit::Code(0).success());
+        assert!(!Exit::Signal(9).success());
+        assert_eq!(Exit::Code(3).to_string(), "exited with 3");
+        assert_eq!(Exit::Signal(9).to_string(), "killed by signal 9");
+    }
+
+    #[test]
+    fn cancelling_stops_the_whole_group() {
+        let (run, rx) = spawn(sh("sleep 30 & echo $!; wait"), None).unwrap();
+        let mut events = block_on_stream(rx);
+        let Some(Event::Stdout(pid)) = events.next() else {
+            panic!("no pid");
+        };
+        let started = Instant::now();
+        run.cancel();
+        assert_eq!(events.collect::<Vec<_>>(), [Event::Exited(Exit::Cancelled)]);
+        assert!(started.elapsed() < KILL_AFTER);
+        assert!(run.is_finished());
+        // the background sleep was in the group too
+        assert!(wait_for_exit(pid.parse().unwrap()));
+    }
+
+    #[test]
+    fn dropping_kills_commands_ignoring_the_request() {
+        let (run, rx) = spawn(sh("trap '' TERM; echo ready; sleep 30"), None).unwrap();
+        let mut events = block_on_stream(rx);
+        assert_eq!(events.next(), Some(stdout("ready")));
+        let started = Instant::now();
+        drop(run);
+        assert_eq!(events.collect::<Vec<_>>(), [Event::Exited(Exit::Cancelled)]);
+        assert!(started.elapsed() >= KILL_AFTER);
+    }
+
+    #[test]
+    fn timeouts() {
+        let started = Instant::now();
+        assert_eq!(
+            run("echo started; sleep 30", Some(Duration::from_millis(200))),
+            [stdout("started"), Event::Exited(Exit::TimedOut)]
+        );
+        assert!(started.elapsed() < KILL_AFTER);
+        assert_eq!(
+            run("exit 2", Some(Duration::from_secs(30))),
+            [Event::Exited(Exit::Code(2))]
+        );
+    }
+
+    #[test]
+    fn shells_run_in_their_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().canonicalize().unwrap();
+        let (_run, rx) = Shell::new("/bin/sh", &cwd).spawn("pwd -P").unwrap();
+        assert_eq!(
+            block_on_stream(rx).collect::<Vec<_>>(),
+            [stdout(&cwd.to_string_lossy()), Event::Exited(Exit::Code(0))]
+        );
+        assert!(Shell::new("/nonexistent/sh", &cwd).spawn("true").is_err());
+    }
+
+    #[test]
+    fn history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commands.json");
+        let history = CommandHistory::load(path.clone(), Storage::plain());
+        history.record("ls -la").unwrap();
+        history.record("git status").unwrap();
+        history.record("ls -la").unwrap();
+        assert_eq!(history.commands(), ["ls -la", "git status"]);
+
+        let history = CommandHistory::load(path.clone(), Storage::plain());
+        assert_eq!(history.commands(), ["ls -la", "git status"]);
+        let mut items = Vec::new();
+        history.provider().search(" gst ").poll(&mut items);
+        let rows: Vec<(&str, &str)> = items
+            .iter()
+            .map(|item| (item.name.as_str(), item.detail.as_str()))
+            .collect();
+        assert_eq!(rows, [("gst", "Run in shell"), ("git status", "Run again")]);
+        // the typed command isn't offered twice
+        let mut items = Vec::new();
+        history.provider().search("ls -la").poll(&mut items);
+        assert_eq!(items.len(), 1);
+
+        fs::write(&path, "garbage").unwrap();
+        assert!(
+            CommandHistory::load(path.clone(), Storage::plain())
+                .commands()
+                .is_empty()
+        );
+        assert!(path.with_extension("json.corrupt").exists());
+    }
+
+    #[test]
+    fn history_keeps_files_it_cant_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commands.json");
+        let storage = Storage::encrypted(Secret::Key([1; 32]));
+        CommandHistory::load(path.clone(), storage.clone())
+            .record("ls")
+            .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        let history = CommandHistory::load(path.clone(), Storage::without_key());
+        history.record("pwd").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), before);
+        assert_eq!(
+            CommandHistory::load(path.clone(), storage).commands(),
+            ["ls"]
+        );
+
+        // the wrong key is a damaged file, it's moved aside
+        let history = CommandHistory::load(path.clone(), Storage::encrypted(Secret::Key([2; 32])));
+        assert!(history.commands().is_empty());
+        assert_eq!(
+            fs::read(path.with_extension("json.locked")).unwrap(),
+            before
+        );
+    }
+}
@@ -0,0 +1,532 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{
+    DateTime, FixedOffset,
+    format::{Item, StrftimeItems},
+};
+use gpui::Global;
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    search::{MemoryProvider, Search, SearchItem, SearchProvider, Target},
+};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snippet {
+    pub name: String,
+    // typed exactly, brings the snippet to the top
+    #[serde(default)]
+    pub keyword: Option<String>,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SnippetFile {
+    #[serde(default)]
+    snippet: Vec<Snippet>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Part {
+    Text(String),
+    Clipboard,
+    // a strftime format, for both `{date}` and `{time}`
+    Date(String),
+    Cursor,
+    // asked for before expanding
+    Field(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpandError {
+    BadFormat(String),
+    EmptyField,
+    ManyCursors,
+    MissingField(String),
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpandError::BadFormat(format) => write!(f, "invalid date format `{format}`"),
+            ExpandError::EmptyField => write!(f, "`{{field:}}` needs a name"),
+            ExpandError::ManyCursors => write!(f, "only one `{{cursor}}` is allowed"),
+            ExpandError::MissingField(name) => write!(f, "no value for field `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for ExpandError {}
+
+// `{{` and `}}` are literal braces, and braces around anything that isn't a placeholder are
+// kept as they are so code templates don't need escaping
+pub fn parse(body: &str) -> Result<Vec<Part>, ExpandError> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut cursors = 0;
+    let mut rest = body;
+
+    while let Some(idx) = rest.find(['{', '}']) {
+        text.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(|inner| inner.split_once('}'))
+            .and_then(|(inner, after)| Some((placeholder(inner)?, after)));
+        match placeholder {
+            Some((part, after)) => {
+                let part = part?;
+                if part == Part::Cursor {
+                    cursors += 1;
+                    if cursors > 1 {
+                        return Err(ExpandError::ManyCursors);
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(part);
+                rest = after;
+            }
+            None => {
+                text.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+// `None` for anything that isn't a known placeholder
+fn placeholder(inner: &str) -> Option<Result<Part, ExpandError>> {
+    let (name, arg) = match inner.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (inner, None),
+    };
+    let date = |default: &str| {
+        let format = arg.unwrap_or(default).to_string();
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            Err(ExpandError::BadFormat(format))
+        } else {
+            Ok(Part::Date(format))
+        }
+    };
+    Some(match (name.trim(), arg) {
+        ("clipboard", None) => Ok(Part::Clipboard),
+        ("cursor", None) => Ok(Part::Cursor),
+        ("date", _) => date("%Y-%m-%d"),
+        ("time", _) => date("%H:%M"),
+        ("field", Some(arg)) if arg.trim().is_empty() => Err(ExpandError::EmptyField),
+        ("field", Some(arg)) => Ok(Part::Field(arg.trim().to_string())),
+        _ => return None,
+    })
+}
+
+// the fields to ask for, in order of first use
+pub fn fields(body: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    parse(body)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Field(name) => seen.insert(name.clone()).then_some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+// everything expansion depends on, so it stays a pure function of its inputs
+pub struct Context<'a> {
+    pub clipboard: Option<&'a str>,
+    pub now: DateTime<FixedOffset>,
+    pub fields: &'a HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    // byte offset of `{cursor}` in `text`
+    pub cursor: Option<usize>,
+}
+
+impl Expansion {
+    // how far back to move after pasting to land on the cursor
+    pub fn chars_after_cursor(&self) -> usize {
+        self.cursor
+            .map(|cursor| self.text[cursor..].chars().count())
+            .unwrap_or(0)
+    }
+}
+
+pub fn expand(body: &str, context: &Context) -> Result<Expansion, ExpandError> {
+    let mut text = String::new();
+    let mut cursor = None;
+    for part in parse(body)? {
+        match part {
+            Part::Text(part) => text.push_str(&part),
+            Part::Clipboard => text.push_str(context.clipboard.unwrap_or_default()),
+            Part::Date(format) => text.push_str(&context.now.format(&format).to_string()),
+            Part::Cursor => cursor = Some(text.len()),
+            Part::Field(name) => match context.fields.get(&name) {
+                Some(value) => text.push_str(value),
+                None => return Err(ExpandError::MissingField(name)),
+            },
+        }
+    }
+    Ok(Expansion { text, cursor })
+}
+
+#[derive(Debug)]
+pub enum SnippetError {
+    Io(PathBuf, io::Error),
+    Parse(toml::de::Error),
+    Duplicate(String),
+    Body(String, ExpandError),
+}
+
+impl fmt::Display for SnippetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnippetError::Io(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            SnippetError::Parse(err) => write!(f, "invalid snippets: {err}"),
+            SnippetError::Duplicate(name) => write!(f, "more than one snippet named `{name}`"),
+            SnippetError::Body(name, err) => write!(f, "invalid snippet `{name}`: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SnippetError {}
+
+// the snippet library, edited by hand in snippets.toml next to the config
+#[derive(Clone)]
+pub struct Snippets {
+    path: Arc<PathBuf>,
+    snippets: Arc<Mutex<Vec<Snippet>>>,
+}
+
+impl Global for Snippets {}
+
+impl Snippets {
+    pub fn default_path() -> PathBuf {
+        Config::path().with_file_name("snippets.toml")
+    }
+
+    // never fails, a broken file is reported and treated as empty until it's fixed
+    pub fn load(path: PathBuf) -> Self {
+        let snippets = Self {
+            path: Arc::new(path),
+            snippets: Arc::new(Mutex::new(Vec::new())),
+        };
+        if let Err(err) = snippets.reload() {
+            eprintln!("{err}");
+        }
+        snippets
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // keeps the current snippets if the file is invalid
+    pub fn reload(&self) -> Result<(), SnippetError> {
+        let snippets = match fs::read_to_string(self.path.as_ref()) {
+            Ok(text) => Self::parse(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(SnippetError::Io(self.path.to_path_buf(), err)),
+        };
+        *self.snippets.lock().unwrap() = snippets;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Vec<Snippet>, SnippetError> {
+        let file: SnippetFile = toml::from_str(text).map_err(SnippetError::Parse)?;
+        let mut names = HashSet::new();
+        for snippet in &file.snippet {
+            if !names.insert(snippet.name.as_str()) {
+                return Err(SnippetError::Duplicate(snippet.name.clone()));
+            }
+            parse(&snippet.body).map_err(|err| SnippetError::Body(snippet.name.clone(), err))?;
+        }
+        Ok(file.snippet)
+    }
+
+    pub fn snippets(&self) -> Vec<Snippet> {
+        self.snippets.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Snippet> {
+        self.snippets
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|snippet| snippet.name == name)
+            .cloned()
+    }
+
+    pub fn provider(&self) -> SnippetProvider {
+        SnippetProvider {
+            snippets: self.clone(),
+        }
+    }
+}
+
+pub struct SnippetProvider {
+    snippets: Snippets,
+}
+
+impl SearchProvider for SnippetProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let items = self
+            .snippets
+            .snippets()
+            .into_iter()
+            .map(|snippet| SearchItem {
+                detail: match &snippet.keyword {
+                    Some(keyword) => format!("Snippet · {keyword} · {}", preview(&snippet.body)),
+                    None => format!("Snippet · {}", preview(&snippet.body)),
+                },
+                keyword: snippet.keyword,
+                target: Target::Snippet(snippet.name.clone()),
+                name: snippet.name,
+            })
+            .collect();
+        MemoryProvider::new(items).search(query)
+    }
+}
+
+// the body on one line
+fn preview(body: &str) -> String {
+    body.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::search::SearchStatus;
+
+    fn context<'a>(clipboard: Option<&'a str>, fields: &'a HashMap<String, String>) -> Context<'a> {
+        Context {
+            clipboard,
+            now: FixedOffset::east_opt(3600)
+                .unwrap()
+                .with_ymd_and_hms(2026, 10, 17, 9, 5, 0)
+                .unwrap(),
+            fields,
+        }
+    }
+
+    fn text(body: &str) -> String {
+        expand(body, &context(None, &HashMap::new())).unwrap().text
+    }
+
+    #[test]
+    fn parses_placeholders() {
+        assert_eq!(
+            parse("Hi {field: name },{cursor}{clipboard} {time}").unwrap(),
+            [
+                Part::Text("Hi ".to_string()),
+                Part::Field("name".to_string()),
+                Part::Text(",".to_string()),
+                Part::Cursor,
+                Part::Clipboard,
+                Part::Text(" ".to_string()),
+                Part::Date("%H:%M".to_string()),
+            ]
+        );
+        assert_eq!(parse("").unwrap(), []);
+    }
+
+    #[test]
+    fn escaped_braces() {
+        assert_eq!(text("{{date}}"), "{date}");
+        assert_eq!(text("fn main() {{ }}"), "fn main() { }");
+        assert_eq!(text("{{{date}}}"), "{2026-10-17}");
+    }
+
+    #[test]
+    fn unknown_braces_are_kept() {
+        for body in [
+            "{ \"a\": 1 }",
+            "{not a placeholder}",
+            "{cursor:x}",
+            "{field}",
+            "{",
+            "}",
+            "} {",
+            "{date",
+        ] {
+            assert_eq!(text(body), body);
+        }
+        assert_eq!(text("{x} {date}"), "{x} 2026-10-17");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            text("{date} {time} {date:%d/%m %H:%M} {time:%I%p}"),
+            "2026-10-17 09:05 17/10 09:05 09AM"
+        );
+        assert_eq!(
+            parse("{date:%Q}"),
+            Err(ExpandError::BadFormat("%Q".to_string()))
+        );
+        assert_eq!(
+            parse("a {time:%} b"),
+            Err(ExpandError::BadFormat("%".to_string()))
+        );
+    }
+
+    #[test]
+    fn one_cursor() {
+        let fields = HashMap::new();
+        let expansion = expand("a{cursor}b", &context(None, &fields)).unwrap();
+        assert_eq!(expansion.text, "ab");
+        assert_eq!(expansion.cursor, Some(1));
+        assert_eq!(parse("{cursor} {cursor}"), Err(ExpandError::ManyCursors));
+        assert_eq!(
+            parse("{cursor}{{cursor}}{cursor}"),
+            Err(ExpandError::ManyCursors)
+        );
+        // an escaped one doesn't count
+        assert!(parse("{cursor}{{cursor}}").is_ok());
+    }
+
+    #[test]
+    fn fields() {
+        let mut fields = HashMap::new();
+        fields.insert("to".to_string(), "Ann".to_string());
+        fields.insert("from".to_string(), "Bo".to_string());
+        let expansion = expand(
+            "Hi {field:to}, from {field:from} to { field : to }",
+            &context(None, &fields),
+        )
+        .unwrap();
+        assert_eq!(expansion.text, "Hi Ann, from Bo to Ann");
+        assert_eq!(
+            super::fields("{field:to} {field:from} {field:to}"),
+            ["to", "from"]
+        );
+        assert!(super::fields("{date:%Q} {field:to}").is_empty());
+
+        assert_eq!(
+            expand("{field:cc}", &context(None, &fields)),
+            Err(ExpandError::MissingField("cc".to_string()))
+        );
+        assert_eq!(parse("{field: }"), Err(ExpandError::EmptyField));
+    }
+
+    #[test]
+    fn clipboard() {
+        let fields = HashMap::new();
+        let expansion = expand("<{clipboard}>", &context(Some("copied"), &fields)).unwrap();
+        assert_eq!(expansion.text, "<copied>");
+        assert_eq!(text("<{clipboard}>"), "<>");
+    }
+
+    #[test]
+    fn cursor_offsets_count_chars() {
+        let fields = HashMap::new();
+        let expansion = expand("größe {cursor}👋 é", &context(None, &fields)).unwrap();
+        assert_eq!(expansion.cursor, Some("größe ".len()));
+        assert_eq!(expansion.chars_after_cursor(), 3);
+
+        let expansion = expand("{clipboard}{cursor}", &context(Some("日本"), &fields)).unwrap();
+        assert_eq!(expansion.chars_after_cursor(), 0);
+        assert_eq!(
+            expand("no cursor", &context(None, &fields))
+                .unwrap()
+                .chars_after_cursor(),
+            0
+        );
+    }
+
+    #[test]
+    fn snippet_files() {
+        let snippets = Snippets::parse(
+            r#"
+            [[snippet]]
+            name = "Signature"
+            keyword = ";sig"
+            body = "Best,\n{cursor}"
+
+            [[snippet]]
+            name = "Braces"
+            body = "{ }"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].keyword.as_deref(), Some(";sig"));
+        assert_eq!(snippets[1].keyword, None);
+
+        let duplicate =
+            "[[snippet]]\nname = \"a\"\nbody = \"x\"\n[[snippet]]\nname = \"a\"\nbody = \"y\"\n";
+        assert!(matches!(
+            Snippets::parse(duplicate),
+            Err(SnippetError::Duplicate(name)) if name == "a"
+        ));
+        assert!(matches!(
+            Snippets::parse("[[snippet]]\nname = \"a\"\nbody = \"{date:%Q}\"\n"),
+            Err(SnippetError::Body(name, ExpandError::BadFormat(_))) if name == "a"
+        ));
+        assert!(matches!(
+            Snippets::parse("[[snippet]]\nname = \"a\"\nbody = \"x\"\nshortcut = \"y\"\n"),
+            Err(SnippetError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn reloads_and_searches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snippets.toml");
+        assert!(Snippets::load(path.clone()).snippets().is_empty());
+
+        fs::write(
+            &path,
+            "[[snippet]]\nname = \"Signature\"\nkeyword = \";sig\"\nbody = \"Best\"\n\
+             [[snippet]]\nname = \";sig lookalike\"\nbody = \"x\"\n",
+        )
+        .unwrap();
+        let snippets = Snippets::load(path.clone());
+        assert_eq!(snippets.get("Signature").unwrap().body, "Best");
+
+        let mut items = Vec::new();
+        snippets.provider().search(";sig").poll(&mut items);
+        let item = items
+            .iter()
+            .find(|item| item.target == Target::Snippet("Signature".to_string()))
+            .unwrap();
+        assert_eq!(item.detail, "Snippet · ;sig · Best");
+
+        // a broken file keeps what was loaded
+        fs::write(&path, "not toml").unwrap();
+        assert!(snippets.reload().is_err());
+        assert!(snippets.get("Signature").is_some());
+        fs::remove_file(&path).unwrap();
+        snippets.reload().unwrap();
+        assert!(snippets.snippets().is_empty());
+        assert_eq!(
+            snippets.provider().search("").poll(&mut Vec::new()),
+            SearchStatus::Done
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use chrono::Local;
+use objc2::{rc::Retained, runtime::AnyObject};
+use objc2_foundation::{
+    NSArray, NSMetadataItem, NSMetadataItemDisplayNameKey, NSMetadataItemPathKey, NSMetadataQuery,
+    NSPredicate, NSString,
+};
+
+use crate::{
+    files::{self, FileProvider, FileQuery},
+    search::{MemoryProvider, Search, SearchItem, SearchProvider, SearchStatus},
+};
+
+pub struct SpotlightProvider;
+
+impl SearchProvider for SpotlightProvider {
+    // spotlight's LIKE can't do fuzzy matching, so fetch every application and let the
+    // ranking step filter them
+    fn search(&self, _query: &str) -> Box<dyn Search> {
+        let format = NSString::from_str("kMDItemContentType == \"com.apple.application\"");
+        let predicate = unsafe { NSPredicate::predicateWithFormat_argumentArray(&format, None) };
+
+        let q = unsafe { NSMetadataQuery::new() };
+        unsafe {
+            q.setPredicate(Some(predicate.as_ref()));
+            q.startQuery();
+        };
+
+        Box::new(SpotlightSearch {
+            q,
+            limit: usize::MAX,
+            file_names: false,
+        })
+    }
+}
+
+// documents and folders, for a file query like `report kind:pdf modified:<7d`
+pub struct SpotlightFileProvider;
+
+impl SearchProvider for SpotlightFileProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let query = match FileQuery::parse(query, Local::now().fixed_offset()) {
+            Ok(query) if !query.is_empty() => query,
+            Ok(_) => return MemoryProvider::new(Vec::new()).search(""),
+            Err(err) => {
+                eprintln!("invalid file query: {err}");
+                return MemoryProvider::new(Vec::new()).search("");
+            }
+        };
+
+        let format = NSString::from_str(&files::spotlight_predicate(&query));
+        let predicate = unsafe { NSPredicate::predicateWithFormat_argumentArray(&format, None) };
+        let scopes: Vec<Retained<NSString>> = query
+            .scopes
+            .iter()
+            .map(|scope| NSString::from_str(&scope.to_string_lossy()))
+            .collect();
+        let scopes: Vec<&AnyObject> = scopes.iter().map(|scope| scope.as_ref()).collect();
+
+        let q = unsafe { NSMetadataQuery::new() };
+        unsafe {
+            q.setPredicate(Some(predicate.as_ref()));
+            if !scopes.is_empty() {
+                q.setSearchScopes(&NSArray::from_slice(&scopes));
+            }
+            q.startQuery();
+        };
+
+        Box::new(SpotlightSearch {
+            q,
+            limit: FileProvider::MAX_RESULTS,
+            file_names: true,
+        })
+    }
+}
+
+struct SpotlightSearch {
+    q: Retained<NSMetadataQuery>,
+    limit: usize,
+    // display names hide extensions, which tell documents apart
+    file_names: bool,
+}
+
+impl Search for SpotlightSearch {
+    fn poll(&mut self, items: &mut Vec<SearchItem>) -> SearchStatus {
+        if unsafe { self.q.isGathering() } {
+            return SearchStatus::Gathering;
+        }
+
+        let results = unsafe { self.q.results() };
+        items.extend(results.iter().take(self.limit).map(|item| {
+            let item: &NSMetadataItem = item.downcast_ref().unwrap();
+            let path = unsafe { item.valueForAttribute(NSMetadataItemPathKey).unwrap() }
+                .downcast_ref::<NSString>()
+                .unwrap()
+                .to_string();
+            let path = PathBuf::from(path);
+            if self.file_names {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                return SearchItem::file(name, path);
+            }
+            let name = unsafe {
+                item.valueForAttribute(NSMetadataItemDisplayNameKey)
+                    .unwrap()
+            }
+            .downcast_ref::<NSString>()
+            .unwrap()
+            .to_string();
+
+            SearchItem::file(name, path)
+        }));
+
+        SearchStatus::Done
+    }
+
+    fn cancel(&mut self) {
+        unsafe { self.q.stopQuery() };
+    }
+}
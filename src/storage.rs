@@ -0,0 +1,747 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Write},
+    num::NonZeroU32,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use gpui::Global;
+use ring::{
+    aead::{self, AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::config::{KeyKind, StorageConfig};
+
+// an encrypted file is the magic, the header fields below, then the ciphertext and its tag.
+// anything else is read as plaintext, which is what every file was before encryption
+const MAGIC: &[u8; 7] = b"HALCYON";
+pub const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 2 + SALT_LEN + aead::NONCE_LEN;
+
+// how the key was made, so a file written under a passphrase can't be opened as a raw key
+const KDF_NONE: u8 = 0;
+const KDF_PBKDF2: u8 = 1;
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(300_000).unwrap();
+
+const KEYCHAIN_SERVICE: &str = "halcyon";
+const KEYCHAIN_ACCOUNT: &str = "storage";
+pub const PASSPHRASE_VAR: &str = "HALCYON_PASSPHRASE";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Truncated,
+    UnknownVersion(u8),
+    // the key couldn't be loaded, e.g. access to the keychain was denied
+    Key(String),
+    // indistinguishable from a damaged file, the tag covers both
+    WrongKey,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "{err}"),
+            StorageError::Truncated => write!(f, "the file is truncated"),
+            StorageError::UnknownVersion(version) => {
+                write!(f, "unknown storage format version {version}")
+            }
+            StorageError::Key(message) => write!(f, "no storage key: {message}"),
+            StorageError::WrongKey => write!(f, "wrong key, or the file is damaged"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl StorageError {
+    // the file can't ever be read as it is, unlike one whose key is missing for now or that a
+    // newer version wrote
+    pub fn is_damaged(&self) -> bool {
+        matches!(self, StorageError::Truncated | StorageError::WrongKey)
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<StorageError> for io::Error {
+    fn from(err: StorageError) -> Self {
+        match err {
+            StorageError::Io(err) => err,
+            err => io::Error::other(err),
+        }
+    }
+}
+
+// a json file of halcyon's as loaded, with nothing in it when it was missing or unusable
+pub struct Loaded<T> {
+    pub file: Option<T>,
+    // off when the file is there but can't be read now, so it isn't overwritten
+    pub writable: bool,
+}
+
+// read on its own first so a newer schema isn't mistaken for corruption
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+pub enum Secret {
+    Key([u8; 32]),
+    // stretched with pbkdf2 and a salt kept in each file
+    Passphrase(String),
+}
+
+// reads and writes the files halcyon keeps, encrypting them if configured to
+#[derive(Clone)]
+pub struct Storage {
+    inner: Arc<Inner>,
+}
+
+impl Global for Storage {}
+
+// derived keys by kdf and salt
+type KeyCache = HashMap<(u8, [u8; SALT_LEN]), Arc<LessSafeKey>>;
+
+struct Inner {
+    encrypt: bool,
+    // only looked up once there's an encrypted file to read or write, so leaving encryption
+    // off never touches the keychain
+    source: Option<StorageConfig>,
+    secret: OnceLock<Result<Secret, String>>,
+    // for everything this process writes under a passphrase
+    salt: [u8; SALT_LEN],
+    keys: Mutex<KeyCache>,
+    rng: SystemRandom,
+}
+
+impl Storage {
+    fn with(encrypt: bool, source: Option<StorageConfig>, secret: Option<Secret>) -> Self {
+        let rng = SystemRandom::new();
+        let mut salt = [0; SALT_LEN];
+        rng.fill(&mut salt).expect("no system randomness");
+        let cell = OnceLock::new();
+        if let Some(secret) = secret {
+            let _ = cell.set(Ok(secret));
+        }
+        Self {
+            inner: Arc::new(Inner {
+                encrypt,
+                source,
+                secret: cell,
+                salt,
+                keys: Mutex::new(HashMap::new()),
+                rng,
+            }),
+        }
+    }
+
+    // can't read encrypted files
+    #[cfg(test)]
+    pub fn plain() -> Self {
+        Self::with(false, None, None)
+    }
+
+    #[cfg(test)]
+    pub fn encrypted(secret: Secret) -> Self {
+        Self::with(true, None, Some(secret))
+    }
+
+    // like a keychain that refused access
+    #[cfg(test)]
+    pub fn without_key() -> Self {
+        Self::with(true, None, None)
+    }
+
+    pub fn new(config: &StorageConfig) -> Self {
+        Self::with(config.encrypt, Some(config.clone()), None)
+    }
+
+    // a file in the other format is rewritten, so turning encryption on or off migrates
+    // each file the first time it's read
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>, StorageError> {
+        let bytes = fs::read(path)?;
+        let was_encrypted = bytes.starts_with(MAGIC);
+        let bytes = self.open(bytes)?;
+        if was_encrypted != self.inner.encrypt
+            && let Err(err) = self.write(path, &bytes)
+        {
+            eprintln!("failed to migrate {}: {err}", path.display());
+        }
+        Ok(bytes)
+    }
+
+    // never fails, `name` is what the file holds, for messages. files that can't be decrypted
+    // are moved aside for when the right key is back, and ones that aren't json are moved aside
+    // for inspection
+    pub fn load<T: DeserializeOwned>(&self, path: &Path, name: &str, version: u32) -> Loaded<T> {
+        let bytes = match self.read(path) {
+            Ok(bytes) => bytes,
+            Err(StorageError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                return Loaded::empty(true);
+            }
+            Err(err) if err.is_damaged() => {
+                let backup = path.with_extension("json.locked");
+                eprintln!(
+                    "{name} can't be decrypted ({err}), moving it to {}",
+                    backup.display()
+                );
+                let _ = fs::rename(path, backup);
+                return Loaded::empty(true);
+            }
+            // e.g. the keychain prompt was denied, the file is fine and may be readable next time
+            Err(err) => {
+                eprintln!(
+                    "failed to read {name} {} ({err}), not saving it until restarted",
+                    path.display()
+                );
+                return Loaded::empty(false);
+            }
+        };
+
+        let file = match serde_json::from_slice::<Header>(&bytes) {
+            Ok(header) if header.version == version => serde_json::from_slice(&bytes),
+            // written by a newer halcyon, which can still read it
+            Ok(header) => {
+                eprintln!(
+                    "{name} {} has unknown schema version {}, not saving it",
+                    path.display(),
+                    header.version
+                );
+                return Loaded::empty(false);
+            }
+            Err(err) => Err(err),
+        };
+        match file {
+            Ok(file) => Loaded {
+                file: Some(file),
+                writable: true,
+            },
+            Err(err) => {
+                let backup = path.with_extension("json.corrupt");
+                eprintln!(
+                    "{name} is unusable ({err}), moving it to {}",
+                    backup.display()
+                );
+                let _ = fs::rename(path, backup);
+                Loaded::empty(true)
+            }
+        }
+    }
+
+    pub fn write(&self, path: &Path, bytes: &[u8]) -> Result<(), StorageError> {
+        if self.inner.encrypt {
+            write_atomic(path, &self.seal(bytes)?)?;
+        } else {
+            write_atomic(path, bytes)?;
+        }
+        Ok(())
+    }
+
+    pub fn seal(&self, bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let kdf = match self.secret()? {
+            Secret::Key(_) => KDF_NONE,
+            Secret::Passphrase(_) => KDF_PBKDF2,
+        };
+        let mut nonce = [0; aead::NONCE_LEN];
+        self.inner
+            .rng
+            .fill(&mut nonce)
+            .map_err(|_| StorageError::Key("no system randomness".to_string()))?;
+
+        let mut sealed = Vec::with_capacity(HEADER_LEN + bytes.len() + AES_256_GCM.tag_len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&[FORMAT_VERSION, kdf]);
+        sealed.extend_from_slice(&self.inner.salt);
+        sealed.extend_from_slice(&nonce);
+
+        let mut ciphertext = bytes.to_vec();
+        self.key(kdf, self.inner.salt)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                // the header is authenticated too
+                Aad::from(&sealed[..]),
+                &mut ciphertext,
+            )
+            .map_err(|_| StorageError::Key("failed to encrypt".to_string()))?;
+        sealed.append(&mut ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, bytes: Vec<u8>) -> Result<Vec<u8>, StorageError> {
+        if !bytes.starts_with(MAGIC) {
+            return Ok(bytes);
+        }
+        if bytes.len() < HEADER_LEN + AES_256_GCM.tag_len() {
+            return Err(StorageError::Truncated);
+        }
+        let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+        let fields = &header[MAGIC.len()..];
+        let (version, kdf) = (fields[0], fields[1]);
+        if version != FORMAT_VERSION {
+            return Err(StorageError::UnknownVersion(version));
+        }
+        let salt: [u8; SALT_LEN] = fields[2..2 + SALT_LEN].try_into().unwrap();
+        let nonce: [u8; aead::NONCE_LEN] = fields[2 + SALT_LEN..].try_into().unwrap();
+
+        let mut plaintext = ciphertext.to_vec();
+        let len = self
+            .key(kdf, salt)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(header),
+                &mut plaintext,
+            )
+            .map_err(|_| StorageError::WrongKey)?
+            .len();
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+
+    fn secret(&self) -> Result<&Secret, StorageError> {
+        self.inner
+            .secret
+            .get_or_init(|| match &self.inner.source {
+                Some(config) => load_secret(config),
+                None => Err("encryption isn't configured".to_string()),
+            })
+            .as_ref()
+            .map_err(|message| StorageError::Key(message.clone()))
+    }
+
+    // deriving from a passphrase is slow on purpose, so every salt is only derived once
+    fn key(&self, kdf: u8, salt: [u8; SALT_LEN]) -> Result<Arc<LessSafeKey>, StorageError> {
+        if let Some(key) = self.inner.keys.lock().unwrap().get(&(kdf, salt)) {
+            return Ok(key.clone());
+        }
+        let bytes = match (self.secret()?, kdf) {
+            (Secret::Key(bytes), KDF_NONE) => *bytes,
+            (Secret::Passphrase(passphrase), KDF_PBKDF2) => {
+                let mut bytes = [0; 32];
+                pbkdf2::derive(
+                    pbkdf2::PBKDF2_HMAC_SHA256,
+                    PBKDF2_ITERATIONS,
+                    &salt,
+                    passphrase.as_bytes(),
+                    &mut bytes,
+                );
+                bytes
+            }
+            _ => return Err(StorageError::WrongKey),
+        };
+        let key = Arc::new(LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &bytes).expect("key has the wrong length"),
+        ));
+        self.inner
+            .keys
+            .lock()
+            .unwrap()
+            .insert((kdf, salt), key.clone());
+        Ok(key)
+    }
+}
+
+impl<T> Loaded<T> {
+    fn empty(writable: bool) -> Self {
+        Self {
+            file: None,
+            writable,
+        }
+    }
+}
+
+fn load_secret(config: &StorageConfig) -> Result<Secret, String> {
+    match config.key {
+        KeyKind::Keychain => keychain_key().map(Secret::Key),
+        KeyKind::File => file_key(&config.key_file)
+            .map(Secret::Key)
+            .map_err(|err| format!("{}: {err}", config.key_file.display())),
+        KeyKind::Passphrase => match std::env::var(PASSPHRASE_VAR) {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(Secret::Passphrase(passphrase)),
+            _ => Err(format!("${PASSPHRASE_VAR} isn't set")),
+        },
+    }
+}
+
+fn random_key() -> Result<[u8; 32], String> {
+    let mut key = [0; 32];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| "no system randomness".to_string())?;
+    Ok(key)
+}
+
+// a random key in the login keychain, created the first time it's needed
+fn keychain_key() -> Result<[u8; 32], String> {
+    let found = Command::new("security")
+        .args(["find-generic-password", "-s", KEYCHAIN_SERVICE])
+        .args(["-a", KEYCHAIN_ACCOUNT, "-w"])
+        .output()
+        .map_err(|err| format!("failed to run security: {err}"))?;
+    if found.status.success() {
+        return decode_hex(String::from_utf8_lossy(&found.stdout).trim())
+            .ok_or_else(|| "the keychain item isn't a storage key".to_string());
+    }
+
+    let key = random_key()?;
+    // given on stdin rather than as an argument, where other processes could see it
+    let mut security = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("failed to run security: {err}"))?;
+    let command = format!(
+        "add-generic-password -s {KEYCHAIN_SERVICE} -a {KEYCHAIN_ACCOUNT} -w {}\n",
+        encode_hex(&key)
+    );
+    let written = security
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(command.as_bytes()));
+    let status = security.wait().map_err(|err| err.to_string())?;
+    match written {
+        Some(Ok(())) if status.success() => Ok(key),
+        _ => Err("failed to add the key to the keychain".to_string()),
+    }
+}
+
+// raw key bytes, created only readable by the user the first time they're needed
+fn file_key(path: &Path) -> io::Result<[u8; 32]> {
+    match fs::read(path) {
+        Ok(bytes) => bytes
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a 32 byte key")),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let key = random_key().map_err(io::Error::other)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)?
+                .write_all(&key)?;
+            Ok(key)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(text: &str) -> Option<[u8; 32]> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; 32];
+    for (idx, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[idx * 2..idx * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+// write then rename so a crash mid-write can't leave a truncated file, every write gets its own
+// temporary file so concurrent ones can't interleave. the file is only readable by the user, and
+// synced before and after the rename so a power loss can't leave it empty either
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = PathBuf::from(tmp);
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    fs::rename(&tmp, path)?;
+
+    // the rename itself is only durable once the directory is
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::PermissionsExt, thread};
+
+    use super::*;
+
+    fn key(byte: u8) -> Storage {
+        Storage::encrypted(Secret::Key([byte; 32]))
+    }
+
+    fn passphrase(passphrase: &str) -> Storage {
+        Storage::encrypted(Secret::Passphrase(passphrase.to_string()))
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let storage = Storage::plain();
+        storage.write(&path, b"{}").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"{}");
+        assert_eq!(storage.read(&path).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        for storage in [key(1), passphrase("correct horse")] {
+            storage.write(&path, b"secret launches").unwrap();
+            let on_disk = fs::read(&path).unwrap();
+            assert!(on_disk.starts_with(MAGIC));
+            assert!(!on_disk.windows(6).any(|window| window == b"secret"));
+            assert_eq!(storage.read(&path).unwrap(), b"secret launches");
+        }
+        // the salt is in the file, so another process with the passphrase can read it
+        assert_eq!(
+            passphrase("correct horse").read(&path).unwrap(),
+            b"secret launches"
+        );
+    }
+
+    #[test]
+    fn sealing_twice_differs() {
+        let storage = key(1);
+        assert_ne!(
+            storage.seal(b"same").unwrap(),
+            storage.seal(b"same").unwrap()
+        );
+    }
+
+    #[test]
+    fn wrong_key() {
+        let sealed = key(1).seal(b"secret").unwrap();
+        assert!(matches!(
+            key(2).open(sealed.clone()),
+            Err(StorageError::WrongKey)
+        ));
+        // a raw key can't open a file sealed under a passphrase, or the other way around
+        assert!(matches!(
+            passphrase("x").open(sealed),
+            Err(StorageError::WrongKey)
+        ));
+        let sealed = passphrase("x").seal(b"secret").unwrap();
+        assert!(matches!(
+            passphrase("y").open(sealed.clone()),
+            Err(StorageError::WrongKey)
+        ));
+        assert!(matches!(key(1).open(sealed), Err(StorageError::WrongKey)));
+    }
+
+    #[test]
+    fn damaged_files() {
+        let storage = key(1);
+        let sealed = storage.seal(b"secret").unwrap();
+
+        let truncated = sealed[..HEADER_LEN + 3].to_vec();
+        assert!(matches!(
+            storage.open(truncated),
+            Err(StorageError::Truncated)
+        ));
+        // cut inside the ciphertext, the tag no longer matches
+        let shortened = sealed[..sealed.len() - 1].to_vec();
+        assert!(matches!(
+            storage.open(shortened),
+            Err(StorageError::WrongKey)
+        ));
+        let mut flipped = sealed.clone();
+        flipped[HEADER_LEN] ^= 1;
+        assert!(matches!(storage.open(flipped), Err(StorageError::WrongKey)));
+        // the header is authenticated too
+        let mut salted = sealed.clone();
+        salted[MAGIC.len() + 2] ^= 1;
+        assert!(matches!(storage.open(salted), Err(StorageError::WrongKey)));
+
+        let mut newer = sealed;
+        newer[MAGIC.len()] = FORMAT_VERSION + 1;
+        let err = storage.open(newer).unwrap_err();
+        assert!(matches!(err, StorageError::UnknownVersion(_)));
+        assert!(!err.is_damaged());
+    }
+
+    #[test]
+    fn missing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        key(1).write(&path, b"secret").unwrap();
+
+        let unconfigured = Storage::without_key();
+        let err = unconfigured.read(&path).unwrap_err();
+        assert!(matches!(err, StorageError::Key(_)));
+        assert!(!err.is_damaged());
+        assert!(matches!(
+            unconfigured.write(&path, b"{}"),
+            Err(StorageError::Key(_))
+        ));
+        // nothing was overwritten
+        assert_eq!(key(1).read(&path).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn loading_json_files() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct File {
+            version: u32,
+            items: Vec<u32>,
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let load = |storage: &Storage| storage.load::<File>(&path, "test history", 1);
+
+        let loaded = load(&Storage::plain());
+        assert!(loaded.file.is_none() && loaded.writable);
+
+        fs::write(&path, r#"{"version": 1, "items": [1, 2]}"#).unwrap();
+        let loaded = load(&Storage::plain());
+        assert_eq!(
+            loaded.file,
+            Some(File {
+                version: 1,
+                items: vec![1, 2]
+            })
+        );
+        assert!(loaded.writable);
+
+        // a newer schema is kept as it is, and not overwritten
+        let newer = r#"{"version": 2, "entries": {}}"#;
+        fs::write(&path, newer).unwrap();
+        let loaded = load(&Storage::plain());
+        assert!(loaded.file.is_none() && !loaded.writable);
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+
+        // so is a file the key is missing for
+        key(1).write(&path, b"{}").unwrap();
+        let loaded = load(&Storage::without_key());
+        assert!(loaded.file.is_none() && !loaded.writable);
+        assert!(path.exists());
+
+        let loaded = load(&key(2));
+        assert!(loaded.file.is_none() && loaded.writable);
+        assert!(!path.exists());
+        assert!(path.with_extension("json.locked").exists());
+
+        for contents in ["not json", r#"{"version": 1, "items": "x"}"#] {
+            fs::write(&path, contents).unwrap();
+            let loaded = load(&Storage::plain());
+            assert!(loaded.file.is_none() && loaded.writable);
+            assert_eq!(
+                fs::read_to_string(path.with_extension("json.corrupt")).unwrap(),
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn files_migrate_when_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        fs::write(&path, b"{}").unwrap();
+
+        assert_eq!(key(1).read(&path).unwrap(), b"{}");
+        assert!(fs::read(&path).unwrap().starts_with(MAGIC));
+
+        // a plain storage can't open it, but one with the key turns it back into plaintext
+        let storage = Storage::with(false, None, Some(Secret::Key([1; 32])));
+        assert_eq!(storage.read(&path).unwrap(), b"{}");
+        assert_eq!(fs::read(&path).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn concurrent_writes_dont_share_a_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let threads: Vec<_> = (0..8u8)
+            .map(|thread| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        write_atomic(&path, &[thread; 4096]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 4096);
+        assert!(bytes.iter().all(|&byte| byte == bytes[0]));
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn written_files_are_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_atomic(&path, b"secret").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"secret");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn hex_keys() {
+        let key = [0xab; 32];
+        assert_eq!(decode_hex(&encode_hex(&key)), Some(key));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex(&"zz".repeat(32)), None);
+    }
+
+    #[test]
+    fn key_files_are_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys/storage.key");
+        let key = file_key(&path).unwrap();
+        assert_eq!(file_key(&path).unwrap(), key);
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        fs::write(&path, b"short").unwrap();
+        assert!(file_key(&path).is_err());
+    }
+}
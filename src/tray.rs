@@ -0,0 +1,139 @@
+use std::error::Error;
+
+use gpui::Global;
+use tray_icon::{
+    Icon, TrayIcon, TrayIconBuilder,
+    menu::{AboutMetadata, CheckMenuItem, Menu, MenuId, MenuItem, PredefinedMenuItem},
+};
+
+// a template image, so macos tints it to match the menu bar
+const ICON: &[u8] = include_bytes!("../assets/tray.png");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrayCommand {
+    Show,
+    Preferences,
+    ReloadIndex,
+    PauseHotkeys,
+    PauseClipboard,
+    Quit,
+}
+
+impl TrayCommand {
+    const ALL: [TrayCommand; 6] = [
+        TrayCommand::Show,
+        TrayCommand::Preferences,
+        TrayCommand::ReloadIndex,
+        TrayCommand::PauseHotkeys,
+        TrayCommand::PauseClipboard,
+        TrayCommand::Quit,
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            TrayCommand::Show => "show",
+            TrayCommand::Preferences => "preferences",
+            TrayCommand::ReloadIndex => "reload-index",
+            TrayCommand::PauseHotkeys => "pause-hotkeys",
+            TrayCommand::PauseClipboard => "pause-clipboard",
+            TrayCommand::Quit => "quit",
+        }
+    }
+
+    pub fn from_id(id: &MenuId) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| id == command.id())
+    }
+}
+
+// the menu bar icon, the only way to reach halcyon without a hotkey since there's no dock icon
+pub struct Tray {
+    _icon: TrayIcon,
+    pause_hotkeys: CheckMenuItem,
+    pause_clipboard: CheckMenuItem,
+}
+
+impl Global for Tray {}
+
+impl Tray {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let item =
+            |command: TrayCommand, text: &str| MenuItem::with_id(command.id(), text, true, None);
+        let check = |command: TrayCommand, text: &str| {
+            CheckMenuItem::with_id(command.id(), text, true, false, None)
+        };
+        let pause_hotkeys = check(TrayCommand::PauseHotkeys, "Pause Hotkey");
+        let pause_clipboard = check(TrayCommand::PauseClipboard, "Pause Clipboard History");
+
+        let menu = Menu::new();
+        menu.append_items(&[
+            &item(TrayCommand::Show, "Show"),
+            &item(TrayCommand::Preferences, "Preferences…"),
+            &item(TrayCommand::ReloadIndex, "Reload Index"),
+            &pause_hotkeys,
+            &pause_clipboard,
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::about(
+                Some("About halcyon"),
+                Some(AboutMetadata {
+                    name: Some("halcyon".to_string()),
+                    version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                    ..Default::default()
+                }),
+            ),
+            &item(TrayCommand::Quit, "Quit"),
+        ])?;
+
+        let image = image::load_from_memory(ICON)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        let icon = TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_icon(Icon::from_rgba(image.into_raw(), width, height)?)
+            .with_icon_as_template(true)
+            .with_tooltip("halcyon")
+            .build()?;
+
+        Ok(Self {
+            _icon: icon,
+            pause_hotkeys,
+            pause_clipboard,
+        })
+    }
+
+    // keeps the check mark in sync when pausing fails or happens elsewhere
+    pub fn set_hotkeys_paused(&self, paused: bool) {
+        self.pause_hotkeys.set_checked(paused);
+    }
+
+    pub fn set_clipboard_paused(&self, paused: bool) {
+        self.pause_clipboard.set_checked(paused);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn menu_ids_round_trip() {
+        for command in TrayCommand::ALL {
+            assert_eq!(
+                TrayCommand::from_id(&MenuId::new(command.id())),
+                Some(command)
+            );
+        }
+        let ids: HashSet<&str> = TrayCommand::ALL
+            .iter()
+            .map(|command| command.id())
+            .collect();
+        assert_eq!(ids.len(), TrayCommand::ALL.len());
+    }
+
+    #[test]
+    fn unknown_ids() {
+        for id in ["", "about", "Show", "reload_index"] {
+            assert_eq!(TrayCommand::from_id(&MenuId::new(id)), None, "{id}");
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    calc::{self, Number},
+    search::{Answer, Answerer},
+};
+
+use Dimension::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Temperature,
+    Data,
+    Time,
+    Area,
+    Volume,
+    Speed,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    // the first alias is the one shown
+    pub aliases: &'static [&'static str],
+    pub dimension: Dimension,
+    // `value * factor + offset` is the value in the dimension's base unit, both are calculator
+    // expressions so they stay exact
+    pub factor: &'static str,
+    pub offset: &'static str,
+}
+
+impl Unit {
+    pub fn name(&self) -> &'static str {
+        self.aliases[0]
+    }
+
+    fn to_base(&self, value: &Number) -> Option<Number> {
+        value
+            .mul(&calc::evaluate(self.factor).ok()?)
+            .ok()?
+            .add(&calc::evaluate(self.offset).ok()?)
+            .ok()
+    }
+
+    fn in_unit(&self, value: &Number) -> Option<Number> {
+        value
+            .sub(&calc::evaluate(self.offset).ok()?)
+            .ok()?
+            .div(&calc::evaluate(self.factor).ok()?)
+            .ok()
+    }
+}
+
+const fn unit(
+    dimension: Dimension,
+    aliases: &'static [&'static str],
+    factor: &'static str,
+) -> Unit {
+    Unit {
+        aliases,
+        dimension,
+        factor,
+        offset: "0",
+    }
+}
+
+// base units are the metre, gram, kelvin, byte, second, square metre, litre and metre per second
+#[rustfmt::skip]
+pub static UNITS: &[Unit] = &[
+    unit(Length, &["nm", "nanometer", "nanometers", "nanometre", "nanometres"], "1e-9"),
+    unit(Length, &["µm", "um", "micrometer", "micrometers", "micron", "microns"], "1e-6"),
+    unit(Length, &["mm", "millimeter", "millimeters", "millimetre", "millimetres"], "1e-3"),
+    unit(Length, &["cm", "centimeter", "centimeters", "centimetre", "centimetres"], "1e-2"),
+    unit(Length, &["m", "meter", "meters", "metre", "metres"], "1"),
+    unit(Length, &["km", "kilometer", "kilometers", "kilometre", "kilometres"], "1e3"),
+    unit(Length, &["in", "inch", "inches", "\""], "0.0254"),
+    unit(Length, &["ft", "foot", "feet", "'"], "0.3048"),
+    unit(Length, &["yd", "yard", "yards"], "0.9144"),
+    unit(Length, &["mi", "mile", "miles"], "1609.344"),
+    unit(Length, &["nmi", "nautical mile", "nautical miles"], "1852"),
+    unit(Mass, &["µg", "ug", "microgram", "micrograms"], "1e-6"),
+    unit(Mass, &["mg", "milligram", "milligrams"], "1e-3"),
+    unit(Mass, &["g", "gram", "grams"], "1"),
+    unit(Mass, &["kg", "kilogram", "kilograms", "kilo", "kilos"], "1e3"),
+    unit(Mass, &["t", "tonne", "tonnes", "metric ton", "metric tons"], "1e6"),
+    unit(Mass, &["oz", "ounce", "ounces"], "28.349523125"),
+    unit(Mass, &["lb", "lbs", "pound", "pounds"], "453.59237"),
+    unit(Mass, &["st", "stone", "stones"], "6350.29318"),
+    Unit { aliases: &["K", "kelvin"], dimension: Temperature, factor: "1", offset: "0" },
+    Unit { aliases: &["°C", "C", "celsius", "degC"], dimension: Temperature, factor: "1", offset: "273.15" },
+    Unit { aliases: &["°F", "F", "fahrenheit", "degF"], dimension: Temperature, factor: "5/9", offset: "459.67 * 5/9" },
+    unit(Data, &["b", "bit", "bits"], "1/8"),
+    unit(Data, &["B", "byte", "bytes"], "1"),
+    unit(Data, &["kB", "KB", "kilobyte", "kilobytes"], "1e3"),
+    unit(Data, &["MB", "megabyte", "megabytes"], "1e6"),
+    unit(Data, &["GB", "gigabyte", "gigabytes"], "1e9"),
+    unit(Data, &["TB", "terabyte", "terabytes"], "1e12"),
+    unit(Data, &["PB", "petabyte", "petabytes"], "1e15"),
+    unit(Data, &["KiB", "kibibyte", "kibibytes"], "1024"),
+    unit(Data, &["MiB", "mebibyte", "mebibytes"], "1024^2"),
+    unit(Data, &["GiB", "gibibyte", "gibibytes"], "1024^3"),
+    unit(Data, &["TiB", "tebibyte", "tebibytes"], "1024^4"),
+    unit(Data, &["PiB", "pebibyte", "pebibytes"], "1024^5"),
+    unit(Data, &["kb", "Kb", "kbit", "kilobit", "kilobits"], "1e3/8"),
+    unit(Data, &["Mb", "Mbit", "megabit", "megabits"], "1e6/8"),
+    unit(Data, &["Gb", "Gbit", "gigabit", "gigabits"], "1e9/8"),
+    unit(Data, &["Tb", "Tbit", "terabit", "terabits"], "1e12/8"),
+    unit(Time, &["ns", "nanosecond", "nanoseconds"], "1e-9"),
+    unit(Time, &["µs", "us", "microsecond", "microseconds"], "1e-6"),
+    unit(Time, &["ms", "millisecond", "milliseconds"], "1e-3"),
+    unit(Time, &["s", "sec", "secs", "second", "seconds"], "1"),
+    unit(Time, &["min", "mins", "minute", "minutes"], "60"),
+    unit(Time, &["h", "hr", "hrs", "hour", "hours"], "3600"),
+    unit(Time, &["d", "day", "days"], "86400"),
+    unit(Time, &["wk", "week", "weeks"], "604800"),
+    // gregorian averages
+    unit(Time, &["mo", "month", "months"], "2629746"),
+    unit(Time, &["y", "yr", "yrs", "year", "years"], "31556952"),
+    unit(Area, &["mm²", "mm2", "mm^2", "sq mm"], "1e-6"),
+    unit(Area, &["cm²", "cm2", "cm^2", "sq cm"], "1e-4"),
+    unit(Area, &["m²", "m2", "m^2", "sq m", "square meter", "square meters"], "1"),
+    unit(Area, &["ha", "hectare", "hectares"], "1e4"),
+    unit(Area, &["km²", "km2", "km^2", "sq km", "square kilometer", "square kilometers"], "1e6"),
+    unit(Area, &["in²", "in2", "in^2", "sq in", "square inch", "square inches"], "0.0254^2"),
+    unit(Area, &["ft²", "ft2", "ft^2", "sq ft", "square foot", "square feet"], "0.3048^2"),
+    unit(Area, &["yd²", "yd2", "yd^2", "sq yd", "square yard", "square yards"], "0.9144^2"),
+    unit(Area, &["ac", "acre", "acres"], "4046.8564224"),
+    unit(Area, &["mi²", "mi2", "mi^2", "sq mi", "square mile", "square miles"], "1609.344^2"),
+    unit(Volume, &["ml", "mL", "milliliter", "milliliters", "millilitre", "millilitres"], "1e-3"),
+    unit(Volume, &["cl", "cL", "centiliter", "centiliters", "centilitre", "centilitres"], "1e-2"),
+    unit(Volume, &["dl", "dL", "deciliter", "deciliters", "decilitre", "decilitres"], "1e-1"),
+    unit(Volume, &["l", "L", "liter", "liters", "litre", "litres"], "1"),
+    unit(Volume, &["m³", "m3", "m^3", "cubic meter", "cubic meters"], "1e3"),
+    unit(Volume, &["cm³", "cm3", "cm^3", "cc"], "1e-3"),
+    unit(Volume, &["tsp", "teaspoon", "teaspoons"], "0.00492892159375"),
+    unit(Volume, &["tbsp", "tablespoon", "tablespoons"], "0.01478676478125"),
+    unit(Volume, &["fl oz", "floz", "fluid ounce", "fluid ounces"], "0.0295735295625"),
+    unit(Volume, &["cup", "cups"], "0.2365882365"),
+    unit(Volume, &["pt", "pint", "pints"], "0.473176473"),
+    unit(Volume, &["qt", "quart", "quarts"], "0.946352946"),
+    unit(Volume, &["gal", "gallon", "gallons"], "3.785411784"),
+    unit(Volume, &["imp gal", "imperial gallon", "imperial gallons"], "4.54609"),
+    unit(Speed, &["m/s", "mps"], "1"),
+    unit(Speed, &["km/h", "kph", "kmh"], "1/3.6"),
+    unit(Speed, &["mph", "mi/h"], "0.44704"),
+    unit(Speed, &["ft/s", "fps"], "0.3048"),
+    unit(Speed, &["kn", "kt", "knot", "knots"], "1852/3600"),
+];
+
+// exact spelling first, so `b` is a bit and `B` a byte, then ignoring case
+pub fn lookup(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    UNITS
+        .iter()
+        .find(|unit| unit.aliases.contains(&name))
+        .or_else(|| {
+            UNITS.iter().find(|unit| {
+                unit.aliases
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+        })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Conversion {
+    pub amount: Number,
+    pub from: &'static Unit,
+    pub to: &'static Unit,
+    pub result: Number,
+}
+
+const CONNECTORS: [&str; 5] = ["in", "to", "as", "into", "->"];
+
+// "<amount> <unit> in <unit>", where the amount is any calculator expression
+pub fn convert(query: &str) -> Option<Conversion> {
+    let words: Vec<(usize, &str)> = query
+        .split_whitespace()
+        .map(|word| (word.as_ptr() as usize - query.as_ptr() as usize, word))
+        .collect();
+
+    // "5 in in cm" has a connector that's also a unit, so try every split
+    words
+        .iter()
+        .filter(|(_, word)| CONNECTORS.contains(&word.to_lowercase().as_str()))
+        .find_map(|&(at, word)| {
+            let (from, to) = (&query[..at], &query[at + word.len()..]);
+            let to = lookup(to)?;
+            let (amount, from) = amount_and_unit(from)?;
+            if from.dimension != to.dimension {
+                return None;
+            }
+            let result = to.in_unit(&from.to_base(&amount)?)?;
+            Some(Conversion {
+                amount,
+                from,
+                to,
+                result,
+            })
+        })
+}
+
+// splits off the longest trailing unit that leaves a valid amount, "72f" works as well as "72 f"
+fn amount_and_unit(text: &str) -> Option<(Number, &'static Unit)> {
+    let text = text.trim();
+    text.char_indices().skip(1).find_map(|(idx, _)| {
+        let unit = lookup(&text[idx..])?;
+        let amount = calc::evaluate(&text[..idx]).ok()?;
+        Some((amount, unit))
+    })
+}
+
+pub struct UnitAnswerer;
+
+impl Answerer for UnitAnswerer {
+    fn answer(&self, query: &str) -> Vec<Answer> {
+        let Some(conversion) = convert(query) else {
+            return Vec::new();
+        };
+        vec![Answer {
+            value: conversion.result.to_string(),
+            detail: format!(
+                "{} {} in {}",
+                conversion.amount,
+                conversion.from.name(),
+                conversion.to.name()
+            ),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(query: &str) -> Option<(String, String)> {
+        let mut answers = UnitAnswerer.answer(query);
+        assert!(answers.len() <= 1);
+        answers.pop().map(|answer| (answer.value, answer.detail))
+    }
+
+    // checked against the calculator so no rounding hides a wrong factor
+    fn exact(query: &str, expected: &str) {
+        let conversion = convert(query).unwrap();
+        assert!(
+            matches!(conversion.result, Number::Exact(_)),
+            "{query} = {:?}",
+            conversion.result
+        );
+        assert_eq!(
+            conversion.result,
+            calc::evaluate(expected).unwrap(),
+            "{query}"
+        );
+    }
+
+    #[test]
+    fn answers() {
+        let answer = |query| answer(query).unwrap();
+        assert_eq!(answer("5 km in mi").1, "5 km in mi");
+        assert_eq!(answer("72f to c").1, "72 °F in °C");
+        assert_eq!(
+            answer("3.5 GiB in MB"),
+            ("3758.096384".into(), "3.5 GiB in MB".into())
+        );
+        assert_eq!(answer("90 min in h"), ("1.5".into(), "90 min in h".into()));
+        assert_eq!(answer("2 * 3 kg as lb").1, "6 kg in lb");
+    }
+
+    #[test]
+    fn one_exact_result_per_dimension() {
+        exact("5 km in mi", "5000 / 1609.344");
+        exact("1 lb in g", "453.59237");
+        exact("72f to c", "(72 - 32) * 5/9");
+        exact("3.5 GiB in MB", "3.5 * 1024^3 / 1e6");
+        exact("90 min in h", "1.5");
+        exact("1 acre in m2", "4046.8564224");
+        exact("1 gal in l", "3.785411784");
+        exact("36 km/h in m/s", "10");
+    }
+
+    #[test]
+    fn connector_that_is_also_a_unit() {
+        let conversion = convert("5 in in cm").unwrap();
+        assert_eq!(conversion.from.name(), "in");
+        assert_eq!(conversion.to.name(), "cm");
+        assert_eq!(conversion.result, calc::evaluate("12.7").unwrap());
+        assert_eq!(convert("5 in to cm"), Some(conversion));
+    }
+
+    #[test]
+    fn bits_and_bytes() {
+        assert_eq!(lookup("b").unwrap().name(), "b");
+        assert_eq!(lookup("B").unwrap().name(), "B");
+        assert_eq!(lookup("Mb").unwrap().name(), "Mb");
+        assert_eq!(lookup("MB").unwrap().name(), "MB");
+        // no exact match, so case is ignored
+        assert_eq!(lookup("mB").unwrap().name(), "MB");
+        exact("8 b in B", "1");
+        exact("100 Mb in MB", "12.5");
+    }
+
+    #[test]
+    fn rejects_other_dimensions() {
+        for query in [
+            "5 km in kg",
+            "72f to s",
+            "1 GiB in h",
+            "3 l in m2",
+            "5 in in lb",
+        ] {
+            assert_eq!(convert(query), None, "{query}");
+            assert_eq!(answer(query), None, "{query}");
+        }
+        // not conversions at all
+        for query in ["5 km", "km in mi", "5 parsecs in km", "5 km in"] {
+            assert_eq!(convert(query), None, "{query}");
+        }
+    }
+}
@@ -1,43 +1,122 @@
-use std::{path::PathBuf, sync::mpsc::Sender, time::Duration};
+use std::{collections::HashMap, rc::Rc, sync::mpsc::Sender, time::Duration};
 
+use chrono::{Local, Utc};
+use futures::{StreamExt, channel::mpsc::UnboundedReceiver};
 use gpui::{
-    App, AppContext, AsyncApp, Context, Entity, FocusHandle, Focusable, InteractiveElement,
-    IntoElement, KeyDownEvent, ParentElement, Render, ScrollHandle, Size,
-    StatefulInteractiveElement, Styled, WeakEntity, Window, div, hsla, px,
+    App, AppContext, AsyncApp, Context, Entity, FocusHandle, Focusable, Font, FontWeight,
+    InteractiveElement, IntoElement, KeyBinding, ParentElement, Render, ScrollHandle, Size,
+    StatefulInteractiveElement, Styled, StyledText, Task, TextRun, WeakEntity, Window, actions,
+    div, hsla, prelude::FluentBuilder, px,
 };
-use objc2::rc::Retained;
-use objc2_foundation::{
-    NSArray, NSMetadataItem, NSMetadataItemDisplayNameKey, NSMetadataItemPathKey, NSMetadataQuery,
-    NSPredicate, NSString,
+
+use crate::{
+    action::{Action, Launcher, SystemLauncher},
+    calc::{self, CalcAnswerer},
+    clipboard::ClipboardHistory,
+    config::{Config, KeysConfig},
+    datetime::DateAnswerer,
+    files::{self, FileProvider},
+    fuzzy::ScoredItem,
+    highlight::{highlight_runs, position_ranges},
+    history::History,
+    indexer::Indexer,
+    input::{Highlighter, TextInput},
+    mode::Mode,
+    panel::Panel,
+    query::{Highlight, Parser},
+    script::{self, OutputMode, Script, ScriptRunProvider, Scripts},
+    search::{self, Answer, Route, SearchProvider, SearchQuery, Searcher, Target},
+    shell::{self, CommandHistory, Event, Exit, Run, Shell},
+    snippet::{self, Snippet, Snippets},
+    spotlight::{SpotlightFileProvider, SpotlightProvider},
+    units::UnitAnswerer,
+    web::{QuicklinkProvider, UrlProvider},
 };
 
-use crate::{input::TextInput, panel::Panel};
+actions!(
+    view,
+    [SelectNext, SelectPrev, Open, Reveal, CopyPath, Delete, Hide]
+);
+
+pub fn bind_keys(cx: &mut App, keys: &KeysConfig) {
+    fn bind<A: gpui::Action + Clone>(keys: &[String], action: A) -> Vec<KeyBinding> {
+        keys.iter()
+            .map(|key| KeyBinding::new(key, action.clone(), Some("View")))
+            .collect()
+    }
+
+    cx.bind_keys(
+        [
+            bind(&keys.select_next, SelectNext),
+            bind(&keys.select_prev, SelectPrev),
+            bind(&keys.open, Open),
+            bind(&keys.reveal, Reveal),
+            bind(&keys.copy_path, CopyPath),
+            bind(&keys.delete, Delete),
+            bind(&keys.hide, Hide),
+        ]
+        .concat(),
+    );
+}
 
 pub struct View {
     cur_idx: usize,
     focus_handle: FocusHandle,
     scroll_handle: ScrollHandle,
     pub input: Entity<TextInput>,
+    launcher: Rc<dyn Launcher>,
+    panel: WeakEntity<Panel>,
+    mode: Mode,
+    query: String,
+    generation: u64,
     query_tx: Sender<SearchQuery>,
+    answers: Vec<Answer>,
+    results: Vec<ScoredItem>,
+    search_task: Option<Task<()>>,
+    // set while asking for a snippet's fields or a script's arguments, which replaces searching
+    prompt: Option<Prompt>,
+    // shown instead of the results once a command or script is run
+    output: Option<Output>,
 }
 
-pub struct SearchQuery {
-    query: String,
-    response_tx: oneshot::Sender<SearchResponse>,
+struct Output {
+    command: String,
+    // with whether each came from stderr
+    lines: Vec<(String, bool)>,
+    // every stdout line, for scripts whose output is used, so the cap on `lines` can't cut it
+    stdout: Vec<String>,
+    exit: Option<Exit>,
+    // what a script's stdout is for once it succeeds, commands only show it
+    mode: OutputMode,
+    run: Run,
+    _task: Task<()>,
+}
+
+impl Output {
+    // older lines are dropped
+    const MAX_LINES: usize = 5000;
 }
 
-struct CurrentQuery {
-    q: Retained<NSMetadataQuery>,
-    tx: oneshot::Sender<SearchResponse>,
+struct Prompt {
+    subject: Subject,
+    fields: Vec<String>,
+    // filled in the order of `fields`
+    values: HashMap<String, String>,
 }
 
-struct SearchItem {
-    name: String,
-    path: PathBuf,
+enum Subject {
+    Snippet(Snippet),
+    // with the arguments typed after its keyword, the missing required ones are the fields
+    Script(Script, Vec<String>),
 }
 
-pub struct SearchResponse {
-    results: Vec<SearchItem>,
+impl Subject {
+    fn name(&self) -> &str {
+        match self {
+            Subject::Snippet(snippet) => &snippet.name,
+            Subject::Script(script, _) => &script.manifest.title,
+        }
+    }
 }
 
 impl View {
@@ -45,79 +124,146 @@ impl View {
         self.input.read(cx).content().is_empty()
     }
 
-    pub fn new(cx: &mut App, window: &Window) -> Self {
-        let (query_tx, query_rx) = std::sync::mpsc::channel::<SearchQuery>();
+    // whether the panel only needs room for the input
+    pub fn is_collapsed(&self, cx: &App) -> bool {
+        self.is_input_empty(cx) && !self.mode.lists_when_empty() && self.output.is_none()
+    }
 
-        cx.background_spawn(async move {
-            let mut cur: Option<CurrentQuery> = None;
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
 
-            loop {
-                if let Ok(query) = query_rx.try_recv() {
-                    if let Some(cur) = cur.take() {
-                        unsafe { cur.q.stopQuery() };
-                    }
+    pub fn new(
+        cx: &mut Context<Self>,
+        window: &Window,
+        panel: WeakEntity<Panel>,
+        mode: Mode,
+    ) -> Self {
+        let (query_tx, query_rx) = std::sync::mpsc::channel::<SearchQuery>();
 
-                    let SearchQuery { query , response_tx } = query;
-                    let search_query = NSString::from_str(query.as_str());
+        let config = cx.global::<Config>().clone();
 
-                    cur = Some(CurrentQuery {
-                        q: unsafe { NSMetadataQuery::new() },
-                        tx: response_tx,
-                    });
-                    let format = NSString::from_str(
-                        "kMDItemDisplayName LIKE %@, kMDItemContentType == \"com.apple.application\"",
+        let file_providers = || -> Vec<Box<dyn SearchProvider>> {
+            if config.search.spotlight {
+                vec![Box::new(SpotlightFileProvider)]
+            } else {
+                let files = &config.files;
+                vec![Box::new(FileProvider::new(
+                    files.roots.clone(),
+                    files.max_depth,
+                ))]
+            }
+        };
+
+        let mut providers: Vec<Box<dyn SearchProvider>> = Vec::new();
+        if mode == Mode::Files {
+            providers = file_providers();
+        }
+        if let Mode::Search | Mode::PasteClipboard = mode {
+            if config.web.detect_urls {
+                providers.push(Box::new(UrlProvider));
+            }
+            if config.search.spotlight {
+                providers.push(Box::new(SpotlightProvider));
+            }
+            if let Some(indexer) = cx.try_global::<Indexer>() {
+                providers.push(Box::new(indexer.provider()));
+            }
+            if let Some(scripts) = cx.try_global::<Scripts>() {
+                providers.push(Box::new(scripts.provider()));
+            }
+        }
+        if let Mode::Search | Mode::PasteClipboard | Mode::Snippets = mode
+            && let Some(snippets) = cx.try_global::<Snippets>()
+        {
+            providers.push(Box::new(snippets.provider()));
+        }
+        if mode == Mode::Clipboard
+            && let Some(history) = cx.try_global::<ClipboardHistory>()
+        {
+            providers.push(Box::new(history.provider()));
+        }
+        let scripts = cx
+            .try_global::<Scripts>()
+            .map(Scripts::scripts)
+            .unwrap_or_default();
+        let mut searcher = Searcher::new(providers)
+            .with_debounce(Duration::from_millis(config.search.debounce_ms));
+        match mode {
+            Mode::Search | Mode::PasteClipboard => {
+                searcher = searcher
+                    .with_answerer(CalcAnswerer::default())
+                    .with_answerer(UnitAnswerer)
+                    .with_answerer(DateAnswerer::system())
+                    .with_route(
+                        Route::new(config.files.prefixes.clone(), file_providers())
+                            .with_ranker(files::rank),
+                    )
+                    .with_route(
+                        Route::new(vec![calc::TRIGGER.to_string()], Vec::new())
+                            .with_answerer(CalcAnswerer::default().with_trivial(true))
+                            .with_answerer(UnitAnswerer)
+                            .with_answerer(DateAnswerer::system()),
                     );
-                    let args = NSArray::new();
-                    unsafe { args.arrayByAddingObject(search_query.as_ref()) };
-                    let predicate = unsafe {
-                        NSPredicate::predicateWithFormat_argumentArray(
-                            format.as_ref(),
-                            Some(args.as_ref()),
+                for (keyword, template) in &config.web.quicklinks {
+                    searcher = searcher.with_route(
+                        Route::new(
+                            vec![keyword.clone()],
+                            vec![Box::new(QuicklinkProvider::new(template.clone()))],
                         )
-                    };
-                    if let Some(query) = cur.as_mut() {
-                        unsafe {
-                            query.q.setPredicate(Some(predicate.as_ref()));
-                            query.q.startQuery();
-                        };
-                    }
+                        .with_ranker(search::unranked),
+                    );
                 }
-
-                if let Some(cur) = cur.take() {
-                    let CurrentQuery { q, tx } = cur;
-                    if !unsafe { q.isGathering() } {
-                        let results = unsafe { q.results() };
-                        let results: Vec<SearchItem> = results.iter().map(|item| {
-                            let item: &NSMetadataItem = item.downcast_ref().unwrap();
-                            let path = unsafe {
-                                item.valueForAttribute(NSMetadataItemPathKey).unwrap()
-                            }
-                            .downcast_ref::<NSString>()
-                            .unwrap()
-                            .to_string();
-                            let path = PathBuf::from(path);
-                            let name = unsafe {
-                                item.valueForAttribute(NSMetadataItemDisplayNameKey).unwrap()
-                            }
-                            .downcast_ref::<NSString>()
-                            .unwrap()
-                            .to_string();
-
-                            SearchItem {
-                                path,
-                                name,
-                            }
-                        }).collect();
-
-                        tx.send(SearchResponse { results }).unwrap();
+                for script in &scripts {
+                    if let Some(keyword) = &script.manifest.keyword {
+                        searcher = searcher.with_route(
+                            Route::new(
+                                vec![keyword.clone()],
+                                vec![Box::new(ScriptRunProvider::new(script.clone()))],
+                            )
+                            .with_ranker(search::unranked),
+                        );
                     }
                 }
+                if let Some(history) = cx.try_global::<CommandHistory>() {
+                    searcher = searcher.with_route(
+                        Route::new(
+                            vec![config.shell.prefix.clone()],
+                            vec![Box::new(history.provider())],
+                        )
+                        .with_ranker(search::unranked),
+                    );
+                }
+                if !config.web.fallback.is_empty() {
+                    searcher =
+                        searcher.with_fallback(QuicklinkProvider::new(config.web.fallback.clone()));
+                }
             }
-        })
-        .detach();
+            Mode::Calculator => {
+                searcher = searcher
+                    .with_answerer(CalcAnswerer::default().with_trivial(true))
+                    .with_answerer(UnitAnswerer)
+                    .with_answerer(DateAnswerer::system());
+            }
+            Mode::Files => searcher = searcher.with_ranker(files::rank),
+            Mode::Clipboard | Mode::Snippets => {}
+        }
+        if let Some(history) = cx.try_global::<History>() {
+            searcher = searcher.with_history(history.clone());
+        }
+        // the searcher blocks on the channel, so give it a thread of its own
+        std::thread::Builder::new()
+            .name("search".into())
+            .spawn(move || searcher.run(query_rx))
+            .expect("failed to spawn search thread");
 
         let input = cx.new(|cx| {
-            let input = TextInput::new(cx);
+            let placeholder = match mode.placeholder() {
+                Some(placeholder) => placeholder.to_string(),
+                None => config.input.placeholder.clone(),
+            };
+            let mut input = TextInput::new(cx, placeholder);
+            input.set_highlighter(highlighter(mode, &config, &scripts), cx);
             cx.spawn(|this: WeakEntity<TextInput>, cx: &mut AsyncApp| {
                 let mut cx = cx.clone();
                 async move {
@@ -146,26 +292,523 @@ impl View {
         });
 
         let window_handle = window.window_handle();
-        cx.observe(&input, move |input, cx| {
-            if !input.read(cx).content().is_empty() {
-                let _ = window_handle.update(cx, |_view, window, _cx| {
-                    window.resize(Size::new(px(Panel::WIDTH), px(Panel::EXPANDED_HEIGHT)));
-                });
+        cx.observe(&input, move |this, input, cx| {
+            let query = input.read(cx).content().to_string();
+            let panel = cx.global::<Config>().panel.clone();
+            let height = if query.is_empty() && !mode.lists_when_empty() {
+                panel.height
             } else {
-                let _ = window_handle.update(cx, |_view, window, _cx| {
-                    window.resize(Size::new(px(Panel::WIDTH), px(Panel::HEIGHT)));
-                });
-            }
+                panel.expanded_height
+            };
+            let _ = window_handle.update(cx, |_view, window, _cx| {
+                window.resize(Size::new(px(panel.width), px(height)));
+            });
+            this.search(query, cx);
         })
         .detach();
 
-        Self {
+        if mode == Mode::PasteClipboard {
+            if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
+                input.update(cx, |input, cx| {
+                    input.set_content(text.replace('\n', " "), cx)
+                });
+            }
+        }
+
+        let mut view = Self {
             cur_idx: 0,
             focus_handle: cx.focus_handle(),
             scroll_handle: ScrollHandle::new(),
+            launcher: Rc::new(SystemLauncher),
+            panel,
+            mode,
+            query: String::new(),
+            generation: 0,
             query_tx,
+            answers: Vec::new(),
+            results: Vec::new(),
+            search_task: None,
+            prompt: None,
+            output: None,
             input,
+        };
+        if mode.lists_when_empty() {
+            view.send_query(cx);
+        }
+        view
+    }
+
+    fn search(&mut self, query: String, cx: &mut Context<Self>) {
+        // the input also notifies on cursor blinks, only dispatch when the text changes
+        if query == self.query || self.prompt.is_some() {
+            return;
+        }
+        self.query = query;
+        if self
+            .output
+            .as_ref()
+            .is_some_and(|output| output.exit.is_some())
+        {
+            self.output = None;
         }
+
+        if self.query.is_empty() && !self.mode.lists_when_empty() {
+            self.generation += 1;
+            self.search_task = None;
+            self.answers.clear();
+            self.results.clear();
+            self.select(0, cx);
+            return;
+        }
+        self.send_query(cx);
+    }
+
+    fn send_query(&mut self, cx: &mut Context<Self>) {
+        self.generation += 1;
+        let (response_tx, response_rx) = oneshot::channel();
+        let generation = self.generation;
+        if self
+            .query_tx
+            .send(SearchQuery {
+                query: self.query.clone(),
+                generation,
+                response_tx,
+            })
+            .is_err()
+        {
+            return;
+        }
+
+        self.search_task = Some(cx.spawn(async move |this, cx| {
+            // the searcher drops the sender of any query it skips
+            let Ok(response) = response_rx.await else {
+                return;
+            };
+            let _ = this.update(cx, |this, cx| {
+                if response.generation != this.generation {
+                    return;
+                }
+                this.answers = response.answers;
+                this.results = response.results;
+                this.select(0, cx);
+            });
+        }));
+    }
+
+    fn select(&mut self, idx: usize, cx: &mut Context<Self>) {
+        self.cur_idx = idx;
+        self.scroll_handle.scroll_to_item(idx);
+        cx.notify();
+    }
+
+    // answers come first, then the results
+    fn row_count(&self) -> usize {
+        self.answers.len() + self.results.len()
+    }
+
+    fn select_next(&mut self, cx: &mut Context<Self>) {
+        if self.cur_idx + 1 < self.row_count() {
+            self.select(self.cur_idx + 1, cx);
+        }
+    }
+
+    fn select_prev(&mut self, cx: &mut Context<Self>) {
+        if self.cur_idx > 0 {
+            self.select(self.cur_idx - 1, cx);
+        }
+    }
+
+    fn launch(&mut self, action: Action, cx: &mut Context<Self>) {
+        if let Some(mut prompt) = self.prompt.take() {
+            let field = prompt.fields[prompt.values.len()].clone();
+            let value = self.input.read(cx).content().to_string();
+            prompt.values.insert(field, value);
+            if prompt.values.len() < prompt.fields.len() {
+                self.prompt = Some(prompt);
+                self.ask(cx);
+                return;
+            }
+            match prompt.subject {
+                Subject::Snippet(snippet) => {
+                    self.insert_snippet(&snippet, &prompt.values, action, cx)
+                }
+                Subject::Script(script, mut args) => {
+                    args.extend(
+                        prompt
+                            .fields
+                            .iter()
+                            .map(|field| prompt.values[field].clone()),
+                    );
+                    self.run_script(&script, args, cx);
+                }
+            }
+            return;
+        }
+
+        // every action on an answer copies it, there's nothing to open or reveal
+        if let Some(answer) = self.answers.get(self.cur_idx) {
+            if let Err(err) = self.launcher.copy(&answer.value) {
+                eprintln!("failed to copy {}: {err}", answer.value);
+                return;
+            }
+            self.hide(cx);
+            return;
+        }
+
+        let Some(ScoredItem { item, .. }) = self.results.get(self.cur_idx - self.answers.len())
+        else {
+            return;
+        };
+        match item.target.clone() {
+            Target::Path(path) => {
+                if let Err(err) = action.run(&path, self.launcher.as_ref()) {
+                    eprintln!("failed to {action:?} {}: {err}", path.display());
+                    return;
+                }
+
+                if let Some(history) = cx.try_global::<History>().cloned() {
+                    let query = self.query.clone();
+                    cx.background_spawn(async move {
+                        if let Err(err) = history.record(&path, &query, Utc::now()) {
+                            eprintln!("failed to save launch history: {err}");
+                        }
+                    })
+                    .detach();
+                }
+            }
+            // there's no path to copy, so the command is
+            Target::Command(command) if action == Action::CopyPath => {
+                if let Err(err) = self.launcher.copy(&command) {
+                    eprintln!("failed to copy {command}: {err}");
+                    return;
+                }
+            }
+            Target::Command(command) => {
+                self.run_command(command, cx);
+                return;
+            }
+            // revealing or copying a script is about its file
+            Target::Script { path, .. } if action != Action::Open => {
+                if let Err(err) = action.run(&path, self.launcher.as_ref()) {
+                    eprintln!("failed to {action:?} {}: {err}", path.display());
+                    return;
+                }
+            }
+            Target::Script { path, args } => {
+                let Some(script) = cx
+                    .try_global::<Scripts>()
+                    .and_then(|scripts| scripts.get(&path))
+                else {
+                    return;
+                };
+                let required = script.manifest.required();
+                if args.len() < required {
+                    let fields = script.manifest.arguments[args.len()..required]
+                        .iter()
+                        .map(|argument| argument.name.clone())
+                        .collect();
+                    self.prompt(Subject::Script(script, args), fields, cx);
+                } else {
+                    self.run_script(&script, args, cx);
+                }
+                return;
+            }
+            Target::Text(text) => {
+                if let Err(err) = self.launcher.copy(&text) {
+                    eprintln!("failed to copy {text}: {err}");
+                    return;
+                }
+            }
+            Target::Url(url) => {
+                if let Err(err) = action.run_url(&url, self.launcher.as_ref()) {
+                    eprintln!("failed to {action:?} {url}: {err}");
+                    return;
+                }
+            }
+            // like answers, every action puts the entry back on the clipboard
+            Target::Clip(id) => {
+                let restored = match cx.try_global::<ClipboardHistory>() {
+                    Some(history) => history.restore(id),
+                    None => return,
+                };
+                if let Err(err) =
+                    restored.and_then(|contents| self.launcher.copy_contents(&contents))
+                {
+                    eprintln!("failed to restore clipboard entry {id}: {err}");
+                    return;
+                }
+            }
+            Target::Snippet(name) => {
+                let Some(snippet) = cx
+                    .try_global::<Snippets>()
+                    .and_then(|snippets| snippets.get(&name))
+                else {
+                    return;
+                };
+                let fields = snippet::fields(&snippet.body);
+                if fields.is_empty() {
+                    self.insert_snippet(&snippet, &HashMap::new(), action, cx);
+                } else {
+                    self.prompt(Subject::Snippet(snippet), fields, cx);
+                }
+                return;
+            }
+        }
+        self.hide(cx);
+    }
+
+    fn prompt(&mut self, subject: Subject, fields: Vec<String>, cx: &mut Context<Self>) {
+        self.generation += 1;
+        self.search_task = None;
+        self.answers.clear();
+        self.results.clear();
+        self.prompt = Some(Prompt {
+            subject,
+            fields,
+            values: HashMap::new(),
+        });
+        self.ask(cx);
+    }
+
+    // asks for the next field of whatever is being prompted for
+    fn ask(&mut self, cx: &mut Context<Self>) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+        let placeholder = format!(
+            "{} · {} ({} of {})",
+            prompt.subject.name(),
+            prompt.fields[prompt.values.len()],
+            prompt.values.len() + 1,
+            prompt.fields.len()
+        );
+        self.input.update(cx, |input, cx| {
+            input.set_placeholder(placeholder, cx);
+            input.set_content("", cx);
+        });
+        self.select(0, cx);
+    }
+
+    // every action copies the expanded snippet, opening pastes it too
+    fn insert_snippet(
+        &mut self,
+        snippet: &Snippet,
+        fields: &HashMap<String, String>,
+        action: Action,
+        cx: &mut Context<Self>,
+    ) {
+        let clipboard = cx.read_from_clipboard().and_then(|item| item.text());
+        let context = snippet::Context {
+            clipboard: clipboard.as_deref(),
+            now: Local::now().fixed_offset(),
+            fields,
+        };
+        let expansion = match snippet::expand(&snippet.body, &context) {
+            Ok(expansion) => expansion,
+            Err(err) => {
+                eprintln!("failed to expand snippet {}: {err}", snippet.name);
+                return;
+            }
+        };
+        if let Err(err) = self.launcher.copy(&expansion.text) {
+            eprintln!("failed to copy snippet {}: {err}", snippet.name);
+            return;
+        }
+        self.hide(cx);
+        if action == Action::Open
+            && let Err(err) = self.launcher.paste(expansion.chars_after_cursor())
+        {
+            eprintln!("failed to paste snippet {}: {err}", snippet.name);
+        }
+    }
+
+    // only clipboard entries can be deleted, there's nothing to undo a launch
+    fn delete(&mut self, cx: &mut Context<Self>) {
+        let Some(idx) = self.cur_idx.checked_sub(self.answers.len()) else {
+            return;
+        };
+        let Some(Target::Clip(id)) = self.results.get(idx).map(|result| &result.item.target) else {
+            return;
+        };
+        let Some(history) = cx.try_global::<ClipboardHistory>().cloned() else {
+            return;
+        };
+
+        let id = *id;
+        cx.background_spawn(async move {
+            if let Err(err) = history.remove(id) {
+                eprintln!("failed to save clipboard history: {err}");
+            }
+        })
+        .detach();
+        self.results.remove(idx);
+        self.select(self.cur_idx.min(self.row_count().saturating_sub(1)), cx);
+    }
+
+    // keeps the panel open, the output streams in under the input
+    fn run_command(&mut self, command: String, cx: &mut Context<Self>) {
+        let shell = Shell::from_config(&cx.global::<Config>().shell);
+        let spawned = match shell.spawn(&command) {
+            Ok(spawned) => spawned,
+            Err(err) => {
+                eprintln!("failed to run {command}: {err}");
+                return;
+            }
+        };
+
+        if let Some(history) = cx.try_global::<CommandHistory>().cloned() {
+            let command = command.clone();
+            cx.background_spawn(async move {
+                if let Err(err) = history.record(&command) {
+                    eprintln!("failed to save command history: {err}");
+                }
+            })
+            .detach();
+        }
+        self.stream(command, spawned, OutputMode::Inline, cx);
+    }
+
+    // scripts aren't recorded in the command history, they're already listed
+    fn run_script(&mut self, script: &Script, args: Vec<String>, cx: &mut Context<Self>) {
+        let command = script.command(&args);
+        let spawned = match shell::spawn(command, Some(script.manifest.timeout)) {
+            Ok(spawned) => spawned,
+            Err(err) => {
+                eprintln!("failed to run {}: {err}", script.path.display());
+                return;
+            }
+        };
+        let mut label = script
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for arg in &args {
+            label.push(' ');
+            label.push_str(arg);
+        }
+        self.stream(label, spawned, script.manifest.output, cx);
+    }
+
+    fn stream(
+        &mut self,
+        command: String,
+        (run, events): (Run, UnboundedReceiver<Event>),
+        mode: OutputMode,
+        cx: &mut Context<Self>,
+    ) {
+        // whatever arrived together is shown together
+        let mut events = events.ready_chunks(256);
+        let task = cx.spawn(async move |this, cx| {
+            while let Some(events) = events.next().await {
+                if this
+                    .update(cx, |this, cx| this.show_output(events, cx))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+        self.output = Some(Output {
+            command,
+            lines: Vec::new(),
+            stdout: Vec::new(),
+            exit: None,
+            mode,
+            run,
+            _task: task,
+        });
+        cx.notify();
+    }
+
+    fn show_output(&mut self, events: Vec<Event>, cx: &mut Context<Self>) {
+        let Some(output) = &mut self.output else {
+            return;
+        };
+        for event in events {
+            match event {
+                Event::Stdout(line) => {
+                    if output.mode != OutputMode::Inline {
+                        output.stdout.push(line.clone());
+                    }
+                    output.lines.push((line, false));
+                }
+                Event::Stderr(line) => output.lines.push((line, true)),
+                Event::Exited(exit) => output.exit = Some(exit),
+            }
+        }
+        if output.lines.len() > Output::MAX_LINES {
+            let excess = output.lines.len() - Output::MAX_LINES;
+            output.lines.drain(..excess);
+        }
+        if output.mode != OutputMode::Inline && output.exit.is_some_and(Exit::success) {
+            self.use_output(cx);
+            return;
+        }
+        self.scroll_handle.scroll_to_bottom();
+        cx.notify();
+    }
+
+    // a script that succeeded has its stdout copied or listed, one that failed keeps it shown
+    fn use_output(&mut self, cx: &mut Context<Self>) {
+        let Some(output) = &self.output else {
+            return;
+        };
+        let stdout = output.stdout.join("\n");
+        match output.mode {
+            OutputMode::Inline => {}
+            OutputMode::Copy => {
+                if let Err(err) = self.launcher.copy(&stdout) {
+                    eprintln!("failed to copy the output of {}: {err}", output.command);
+                    return;
+                }
+                self.hide(cx);
+            }
+            OutputMode::Rows => match script::parse_rows(&stdout) {
+                Ok(rows) => {
+                    self.output = None;
+                    self.generation += 1;
+                    self.search_task = None;
+                    self.answers.clear();
+                    self.results = rows
+                        .into_iter()
+                        .map(|item| ScoredItem {
+                            item,
+                            score: 0,
+                            positions: Vec::new(),
+                        })
+                        .collect();
+                    self.select(0, cx);
+                }
+                Err(err) => {
+                    if let Some(output) = &mut self.output {
+                        output.lines.push((err.to_string(), true));
+                    }
+                    self.scroll_handle.scroll_to_bottom();
+                    cx.notify();
+                }
+            },
+        }
+    }
+
+    // escape stops a running command first, then dismisses its output, then hides the panel
+    fn dismiss(&mut self, cx: &mut Context<Self>) {
+        match &self.output {
+            Some(output) if output.exit.is_none() => output.run.cancel(),
+            Some(_) => {
+                self.output = None;
+                cx.notify();
+            }
+            None => self.hide(cx),
+        }
+    }
+
+    fn hide(&self, cx: &mut Context<Self>) {
+        // the panel updates this view's window, so wait until we're out of it
+        let panel = self.panel.clone();
+        cx.defer(move |cx| {
+            let _ = panel.update(cx, |panel, cx| panel.hide(cx));
+        });
     }
 }
 
@@ -176,26 +819,174 @@ impl Focusable for View {
 }
 
 impl Render for View {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let run = TextRun {
+            len: 0,
+            font: window.text_style().font(),
+            color: hsla(0.0, 0.0, 0.9, 1.0),
+            background_color: None,
+            underline: None,
+            strikethrough: None,
+        };
+        let highlight = TextRun {
+            font: Font {
+                weight: FontWeight::BOLD,
+                ..run.font.clone()
+            },
+            color: hsla(0.58, 0.9, 0.7, 1.0),
+            ..run.clone()
+        };
+
+        let cur_idx = self.cur_idx;
+        let answers = self.answers.iter().enumerate().map(move |(idx, answer)| {
+            div()
+                .id(idx)
+                .flex()
+                .flex_col()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .when(idx == cur_idx, |row| row.bg(hsla(0.0, 0.0, 1.0, 0.1)))
+                .child(div().text_xl().child(answer.value.clone()))
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(hsla(0.0, 0.0, 0.9, 0.4))
+                        .truncate()
+                        .child(answer.detail.clone()),
+                )
+        });
+        let offset = self.answers.len();
+        let rows = self.results.iter().enumerate().map(move |(idx, result)| {
+            let idx = idx + offset;
+            let item = &result.item;
+            div()
+                .id(idx)
+                .flex()
+                .flex_col()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .when(idx == cur_idx, |row| row.bg(hsla(0.0, 0.0, 1.0, 0.1)))
+                .child(
+                    StyledText::new(item.name.clone()).with_runs(highlight_runs(
+                        &item.name,
+                        &position_ranges(&item.name, &result.positions)
+                            .into_iter()
+                            .map(|range| (range, highlight.clone()))
+                            .collect::<Vec<_>>(),
+                        &run,
+                    )),
+                )
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(hsla(0.0, 0.0, 0.9, 0.4))
+                        .truncate()
+                        .child(item.detail.clone()),
+                )
+        });
+
+        let output = self.output.as_ref().map(|output| {
+            let (status, failed) = match output.exit {
+                Some(exit) => (exit.to_string(), !exit.success()),
+                None => ("running, escape cancels".to_string(), false),
+            };
+            div()
+                .flex()
+                .flex_col()
+                .font_family("Menlo")
+                .text_sm()
+                .child(
+                    div()
+                        .text_color(hsla(0.0, 0.0, 0.9, 0.4))
+                        .child(format!("$ {}", output.command)),
+                )
+                .children(output.lines.iter().map(|(line, stderr)| {
+                    div()
+                        .when(*stderr, |line| line.text_color(hsla(0.0, 0.7, 0.65, 1.0)))
+                        .child(line.clone())
+                }))
+                .child(
+                    div()
+                        .pt_1()
+                        .text_color(if failed {
+                            hsla(0.0, 0.7, 0.65, 1.0)
+                        } else {
+                            hsla(0.0, 0.0, 0.9, 0.4)
+                        })
+                        .child(status),
+                )
+        });
+        let showing_output = output.is_some();
+
         div()
-            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
-                match event.keystroke.key.as_str() {
-                    "escape" => {
-                        cx.hide();
-                        window.remove_window();
-                    }
-                    _ => {}
-                }
-            }))
+            .key_context("View")
+            .on_action(cx.listener(|this, _: &SelectNext, _, cx| this.select_next(cx)))
+            .on_action(cx.listener(|this, _: &SelectPrev, _, cx| this.select_prev(cx)))
+            .on_action(cx.listener(|this, _: &Open, _, cx| this.launch(Action::Open, cx)))
+            .on_action(cx.listener(|this, _: &Reveal, _, cx| this.launch(Action::Reveal, cx)))
+            .on_action(cx.listener(|this, _: &CopyPath, _, cx| this.launch(Action::CopyPath, cx)))
+            .on_action(cx.listener(|this, _: &Delete, _, cx| this.delete(cx)))
+            .on_action(cx.listener(|this, _: &Hide, _, cx| this.dismiss(cx)))
+            .flex()
+            .flex_col()
             .h_full()
             .w_full()
             .text_color(hsla(0.0, 0.0, 0.9, 1.0))
             .bg(hsla(0.0, 0.0, 0.08, 0.5))
-            .id("input")
             .track_focus(&self.focus_handle.clone())
-            .overflow_x_scroll()
-            .track_scroll(&self.scroll_handle.clone())
             .p_5()
             .child(self.input.clone())
+            .child(
+                div()
+                    .id("results")
+                    .flex_1()
+                    .min_h_0()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle.clone())
+                    .children(output)
+                    .when(!showing_output, |results| {
+                        results.children(answers).children(rows)
+                    }),
+            )
     }
 }
+
+// colors the prefix a query was routed by, and file filters wherever they apply
+fn highlighter(mode: Mode, config: &Config, scripts: &[Script]) -> Highlighter {
+    let file_prefixes = config.files.prefixes.clone();
+    let mut parser = Parser::new().with_keys(files::KEYS);
+    if let Mode::Search | Mode::PasteClipboard = mode {
+        parser = parser
+            .with_triggers(file_prefixes.clone())
+            .with_triggers([calc::TRIGGER])
+            .with_triggers(config.web.quicklinks.keys().cloned())
+            .with_triggers([config.shell.prefix.clone()])
+            .with_triggers(
+                scripts
+                    .iter()
+                    .filter_map(|script| script.manifest.keyword.clone()),
+            );
+    }
+
+    Rc::new(move |text: &str| {
+        let query = parser.parse(text);
+        let filters = match &query.trigger {
+            Some(trigger) => file_prefixes.contains(&trigger.keyword),
+            None => mode == Mode::Files,
+        };
+        query
+            .highlights()
+            .into_iter()
+            .filter(|(_, highlight)| filters || *highlight == Highlight::Trigger)
+            .map(|(range, highlight)| {
+                let color = match highlight {
+                    Highlight::Trigger | Highlight::Key => hsla(0.58, 0.9, 0.7, 1.0),
+                    Highlight::Negation => hsla(0.0, 0.7, 0.65, 1.0),
+                };
+                (range, color)
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,393 @@
+use std::{fmt, net::Ipv4Addr};
+
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use url::{Url, form_urlencoded};
+
+use crate::search::{MemoryProvider, Search, SearchItem, SearchProvider, Target};
+
+// replaced by the search in a quicklink, e.g. `https://github.com/search?q={query}`
+pub const PLACEHOLDER: &str = "{query}";
+
+// a whole path segment, so a slash in the search can't add another one
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+const FRAGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`');
+
+// top level domains an address without a scheme has to end in, so file names like
+// `notes.md` aren't taken for one
+const TLDS: [&str; 30] = [
+    "com", "net", "org", "edu", "gov", "io", "dev", "app", "ai", "co", "me", "so", "sh", "gg",
+    "tv", "fm", "xyz", "info", "biz", "uk", "de", "fr", "nl", "jp", "ca", "au", "us", "eu", "ch",
+    "se",
+];
+
+// the part of a url a placeholder is in, each needs its own percent-encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Component {
+    Host,
+    Path,
+    Query,
+    Fragment,
+}
+
+impl Component {
+    // the component the template ends in, given everything before a placeholder
+    pub fn at_end_of(template: &str) -> Component {
+        if template.contains('#') {
+            Component::Fragment
+        } else if template.contains('?') {
+            Component::Query
+        } else {
+            match template.split_once("://") {
+                Some((_, after)) if !after.contains('/') => Component::Host,
+                // including opaque paths like `mailto:{query}`
+                _ => Component::Path,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    PlaceholderInHost,
+    Invalid(url::ParseError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::PlaceholderInHost => write!(f, "`{PLACEHOLDER}` can't be in the host"),
+            TemplateError::Invalid(err) => write!(f, "not a valid url: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+// a template without a placeholder is fine, it always opens the same page
+pub fn expand(template: &str, query: &str) -> Result<Url, TemplateError> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(at) = rest.find(PLACEHOLDER) {
+        expanded.push_str(&rest[..at]);
+        match Component::at_end_of(&expanded) {
+            Component::Host => return Err(TemplateError::PlaceholderInHost),
+            Component::Path => expanded.extend(utf8_percent_encode(query, PATH_SEGMENT)),
+            // spaces become `+`, like a submitted search form
+            Component::Query => expanded.extend(form_urlencoded::byte_serialize(query.as_bytes())),
+            Component::Fragment => expanded.extend(utf8_percent_encode(query, FRAGMENT)),
+        }
+        rest = &rest[at + PLACEHOLDER.len()..];
+    }
+    expanded.push_str(rest);
+    Url::parse(&expanded).map_err(TemplateError::Invalid)
+}
+
+// the url the text is, if it looks like one: anything with `://`, or a bare address like
+// `example.com/docs` or `localhost:3000`
+pub fn detect(text: &str) -> Option<Url> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    if text.contains("://") {
+        let url = Url::parse(text).ok()?;
+        return (url.has_host() || url.scheme() == "file").then_some(url);
+    }
+
+    let authority = &text[..text.find(['/', '?', '#']).unwrap_or(text.len())];
+    // an email address
+    if authority.contains('@') {
+        return None;
+    }
+    let host = match authority.split_once(':') {
+        Some((host, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => host,
+        Some(_) => return None,
+        None => authority,
+    };
+    let scheme = if host == "localhost" || host.parse::<Ipv4Addr>().is_ok() {
+        "http"
+    } else {
+        let labels_ok = host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        });
+        let (_, tld) = host.rsplit_once('.')?;
+        if !labels_ok || !TLDS.contains(&tld.to_ascii_lowercase().as_str()) {
+            return None;
+        }
+        "https"
+    };
+    Url::parse(&format!("{scheme}://{text}")).ok()
+}
+
+// searches a site with whatever follows a quicklink's keyword, or the web when nothing else
+// matched
+pub struct QuicklinkProvider {
+    template: String,
+}
+
+impl QuicklinkProvider {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    // e.g. "github.com", for naming the row
+    fn site(url: &Url) -> String {
+        match url.host_str() {
+            Some(host) => host.trim_start_matches("www.").to_string(),
+            None => url.scheme().to_string(),
+        }
+    }
+}
+
+impl SearchProvider for QuicklinkProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let query = query.trim();
+        let item = expand(&self.template, query)
+            .ok()
+            .filter(|_| !query.is_empty())
+            .map(|url| SearchItem {
+                name: format!("Search {} for \u{201c}{query}\u{201d}", Self::site(&url)),
+                detail: url.to_string(),
+                keyword: None,
+                target: Target::Url(url),
+            });
+        MemoryProvider::new(item.into_iter().collect()).search("")
+    }
+}
+
+// offers to open the query when it's an address
+pub struct UrlProvider;
+
+impl SearchProvider for UrlProvider {
+    fn search(&self, query: &str) -> Box<dyn Search> {
+        let item = detect(query).map(|url| SearchItem {
+            name: url.to_string(),
+            detail: "Open URL".to_string(),
+            // ranks it first, it's exactly what was typed
+            keyword: Some(query.trim().to_string()),
+            target: Target::Url(url),
+        });
+        MemoryProvider::new(item.into_iter().collect()).search("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchStatus;
+
+    fn expanded(template: &str, query: &str) -> String {
+        expand(template, query).unwrap().to_string()
+    }
+
+    fn detected(text: &str) -> Option<String> {
+        detect(text).map(|url| url.to_string())
+    }
+
+    fn items(provider: &dyn SearchProvider, query: &str) -> Vec<SearchItem> {
+        let mut items = Vec::new();
+        assert_eq!(provider.search(query).poll(&mut items), SearchStatus::Done);
+        items
+    }
+
+    #[test]
+    fn components() {
+        assert_eq!(Component::at_end_of("https://"), Component::Host);
+        assert_eq!(Component::at_end_of("https://en."), Component::Host);
+        assert_eq!(
+            Component::at_end_of("https://example.com/"),
+            Component::Path
+        );
+        assert_eq!(Component::at_end_of("mailto:"), Component::Path);
+        assert_eq!(Component::at_end_of("https://x.com/?q="), Component::Query);
+        assert_eq!(
+            Component::at_end_of("https://x.com?a=1&q="),
+            Component::Query
+        );
+        assert_eq!(
+            Component::at_end_of("https://x.com/?q=1#"),
+            Component::Fragment
+        );
+    }
+
+    #[test]
+    fn encodes_for_each_component() {
+        let query = "a b&c=d/é?#%+";
+        assert_eq!(
+            expanded("https://github.com/search?q={query}", query),
+            "https://github.com/search?q=a+b%26c%3Dd%2F%C3%A9%3F%23%25%2B"
+        );
+        assert_eq!(
+            expanded("https://en.wikipedia.org/wiki/{query}", query),
+            "https://en.wikipedia.org/wiki/a%20b&c=d%2F%C3%A9%3F%23%25+"
+        );
+        assert_eq!(
+            expanded("https://example.com/docs#{query}", query),
+            "https://example.com/docs#a%20b&c=d/%C3%A9?#%25+"
+        );
+        // each placeholder is encoded for where it is
+        assert_eq!(
+            expanded("https://docs.rs/{query}/latest?q={query}#{query}", "x y"),
+            "https://docs.rs/x%20y/latest?q=x+y#x%20y"
+        );
+        assert_eq!(
+            expanded("https://example.com/?a=1&q={query}&b=2", "100%"),
+            "https://example.com/?a=1&q=100%25&b=2"
+        );
+        assert_eq!(
+            expanded("mailto:{query}", "a b@c.com"),
+            "mailto:a%20b@c.com"
+        );
+        // the query can't add placeholders of its own
+        assert_eq!(
+            expanded("https://x.com/?q={query}", "{query}"),
+            "https://x.com/?q=%7Bquery%7D"
+        );
+        assert_eq!(expanded("https://example.com", "x"), "https://example.com/");
+    }
+
+    #[test]
+    fn bad_templates() {
+        assert_eq!(
+            expand("https://{query}.wikipedia.org", "x"),
+            Err(TemplateError::PlaceholderInHost)
+        );
+        assert_eq!(
+            expand("https://example.com:{query}/", "80"),
+            Err(TemplateError::PlaceholderInHost)
+        );
+        // even when the query is empty
+        assert_eq!(
+            expand("https://{query}", ""),
+            Err(TemplateError::PlaceholderInHost)
+        );
+        assert!(matches!(
+            expand("not a url {query}", "x"),
+            Err(TemplateError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn detects_addresses() {
+        assert_eq!(
+            detected("https://example.com/x?y#z").as_deref(),
+            Some("https://example.com/x?y#z")
+        );
+        assert_eq!(
+            detected("  example.com/docs ").as_deref(),
+            Some("https://example.com/docs")
+        );
+        assert_eq!(
+            detected("Sub.Example.IO").as_deref(),
+            Some("https://sub.example.io/")
+        );
+        assert_eq!(
+            detected("example.com:8443?q=1").as_deref(),
+            Some("https://example.com:8443/?q=1")
+        );
+        assert_eq!(detected("file:///tmp/x").as_deref(), Some("file:///tmp/x"));
+    }
+
+    #[test]
+    fn local_addresses_are_http() {
+        assert_eq!(
+            detected("localhost:3000").as_deref(),
+            Some("http://localhost:3000/")
+        );
+        assert_eq!(
+            detected("localhost/admin").as_deref(),
+            Some("http://localhost/admin")
+        );
+        assert_eq!(
+            detected("127.0.0.1:8080/a").as_deref(),
+            Some("http://127.0.0.1:8080/a")
+        );
+        assert_eq!(
+            detected("192.168.1.1").as_deref(),
+            Some("http://192.168.1.1/")
+        );
+        assert_eq!(detected("localhost:").as_deref(), None);
+        assert_eq!(detected("localhost:port").as_deref(), None);
+    }
+
+    #[test]
+    fn rejects_everything_else() {
+        for text in [
+            "",
+            "  ",
+            "notes.md",
+            "main.rs",
+            "2.5",
+            "192.168.1",
+            "a@b.com",
+            "a@b.com/x",
+            "mailto:a@b.com",
+            "hello world.com",
+            "C:\\x",
+            "foo",
+            "x.com:abc",
+            ".com",
+            "a..com",
+            "a_b.com",
+            "about://",
+        ] {
+            assert_eq!(detected(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn quicklinks() {
+        let provider = QuicklinkProvider::new("https://www.google.com/search?q={query}");
+        let found = items(&provider, " rust lang ");
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].name,
+            "Search google.com for \u{201c}rust lang\u{201d}"
+        );
+        assert_eq!(found[0].detail, "https://www.google.com/search?q=rust+lang");
+        assert!(items(&provider, "  ").is_empty());
+
+        let mail = QuicklinkProvider::new("mailto:{query}");
+        assert_eq!(
+            items(&mail, "a@b.com")[0].name,
+            "Search mailto for \u{201c}a@b.com\u{201d}"
+        );
+        // a broken template offers nothing rather than a broken link
+        let broken = QuicklinkProvider::new("https://{query}.example.com");
+        assert!(items(&broken, "x").is_empty());
+    }
+
+    #[test]
+    fn urls() {
+        let found = items(&UrlProvider, " example.com ");
+        assert_eq!(found[0].name, "https://example.com/");
+        assert_eq!(found[0].keyword.as_deref(), Some("example.com"));
+        assert_eq!(
+            found[0].target,
+            Target::Url(Url::parse("https://example.com").unwrap())
+        );
+        assert!(items(&UrlProvider, "safari").is_empty());
+    }
+}